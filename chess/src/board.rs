@@ -108,6 +108,24 @@ impl Board {
 
     pub(crate) fn initialize(&mut self) {
         self.state.zobrist_hash = self.initialize_zobrist_hash();
+        self.state.pawn_hash = self.initialize_pawn_hash();
+    }
+
+    /// Creates the pawn hash by XOR-ing the zobrist values of every pawn on the board.
+    fn initialize_pawn_hash(&self) -> ZobristHash {
+        let mut pawn_hash = ZobristHash::default();
+
+        for side in 0..NumberOf::SIDES {
+            let mut bitboard = self.piece_bitboards[side][Piece::Pawn as usize];
+            while bitboard != 0 {
+                let square = bitboard_helpers::next_bit(&mut bitboard);
+                pawn_hash ^=
+                    self.zobrist_values
+                        .get_piece_value(Piece::Pawn as usize, side, square);
+            }
+        }
+
+        pawn_hash
     }
 
     fn initialize_zobrist_hash(&self) -> ZobristHash {
@@ -234,9 +252,13 @@ impl Board {
     }
 
     pub(crate) fn update_zobrist_hash_for_piece(&mut self, square: u8, piece: Piece, side: Side) {
-        self.state.zobrist_hash ^=
+        let value =
             self.zobrist_values
                 .get_piece_value(piece as usize, side as usize, square as usize);
+        self.state.zobrist_hash ^= value;
+        if piece == Piece::Pawn {
+            self.state.pawn_hash ^= value;
+        }
     }
 
     fn set_zobrist_hash(&mut self, hash: u64) {
@@ -268,6 +290,7 @@ impl Board {
         board.set_side_to_move(Side::White);
         board.set_castling_rights(CastlingAvailability::ALL);
         board.set_zobrist_hash(board.initialize_zobrist_hash());
+        board.state.pawn_hash = board.initialize_pawn_hash();
         board
     }
 
@@ -433,6 +456,12 @@ impl Board {
         self.state.zobrist_hash
     }
 
+    /// Returns the Zobrist hash of the pawns on this [`Board`]. This only changes when a pawn
+    /// moves, is captured or promotes.
+    pub fn pawn_hash(&self) -> u64 {
+        self.state.pawn_hash
+    }
+
     /// Checks if a given square is empty.
     pub fn is_square_empty(&self, square: &Square) -> bool {
         !self
@@ -559,6 +588,16 @@ impl Board {
         self.is_draw_by_fifty_move_rule() || self.insufficient_material() || self.is_repetition()
     }
 
+    /// Checks for draws during a search, `ply` plies after the root position of the search.
+    ///
+    /// This is the same as [`Board::is_draw`], except that repetitions are checked with
+    /// [`Board::is_repetition_in_search`].
+    pub fn is_draw_in_search(&self, ply: usize) -> bool {
+        self.is_draw_by_fifty_move_rule()
+            || self.insufficient_material()
+            || self.is_repetition_in_search(ply)
+    }
+
     /// Check if the game is a draw by insufficient material. We use the FIDE rules for this check.
     ///
     /// Returns true if the game is a draw by insufficient material, otherwise false.
@@ -633,6 +672,42 @@ impl Board {
         repetition_count >= 2
     }
 
+    /// Check if the position is a draw by repetition during a search, `ply` plies after the root
+    /// position of the search.
+    ///
+    /// A position that already occurred after the root only needs to repeat once, since the side
+    /// that repeated it could do so again. Positions from the game history before the root still
+    /// need to repeat twice, as in [`Board::is_repetition`].
+    pub fn is_repetition_in_search(&self, ply: usize) -> bool {
+        let mut repetition_count = 0;
+        for (distance, previous_state) in self.history.iter().rev().enumerate() {
+            // a null move doesn't reset the half-move clock, but positions before it can't be
+            // repeated with real moves
+            if previous_state.next_move.is_null_move() {
+                return false;
+            }
+            // the last position has the other side to move, so it can't be a repetition
+            if distance > 0 && previous_state.zobrist_hash == self.zobrist_hash() {
+                // the earlier position was reached after the root
+                if distance + 1 < ply {
+                    return true;
+                }
+
+                repetition_count += 1;
+                if repetition_count >= MAX_REPETITION_COUNT {
+                    return true;
+                }
+            }
+
+            // there can't be a repeated position before the last irreversible move
+            if previous_state.half_move_clock == 0 {
+                return false;
+            }
+        }
+
+        false
+    }
+
     /// Check if a given move is legal. This function does not alter the current board state.
     /// Instead it makes a copy of the current state and tries to make the move. There is a performance
     /// penalty for this, so use this function sparingly.
@@ -733,6 +808,45 @@ mod tests {
         assert!(board.is_repetition());
     }
 
+    #[test]
+    fn twofold_repetition_in_search() {
+        let mut board = Board::from_fen("k7/8/KQ6/8/8/8/8/8 w - - 0 1").unwrap();
+        for mv in ["b6c5", "a8b8", "c5b6", "b8a8"] {
+            board.make_uci_move(mv).unwrap();
+        }
+
+        // the first repetition only counts if the earlier position is after the root
+        assert!(!board.is_repetition());
+        assert!(board.is_repetition_in_search(5));
+        assert!(!board.is_repetition_in_search(4));
+        assert!(!board.is_draw_in_search(4));
+
+        // game history needs a third occurrence
+        for mv in ["b6c5", "a8b8", "c5b6", "b8a8"] {
+            board.make_uci_move(mv).unwrap();
+        }
+        assert!(board.is_repetition_in_search(0));
+        assert!(board.is_draw_in_search(0));
+    }
+
+    #[test]
+    fn repetition_in_search_stops_at_null_moves() {
+        let mut board = Board::from_fen("k7/8/KQ6/8/8/8/8/8 w - - 0 1").unwrap();
+        let start = board.zobrist_hash();
+        for mv in ["b6c5", "a8b8", "c5d4"] {
+            board.make_uci_move(mv).unwrap();
+        }
+        board.null_move();
+        for mv in ["d4b6", "b8a8"] {
+            board.make_uci_move(mv).unwrap();
+        }
+        assert_eq!(board.zobrist_hash(), start);
+
+        // the start position was only reached again because of the null move
+        assert!(!board.is_repetition_in_search(7));
+        assert!(!board.is_draw_in_search(7));
+    }
+
     #[test]
     fn checkmate() {
         let move_gen = MoveGenerator::new();
@@ -785,6 +899,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn pawn_hash_only_tracks_pawns() {
+        static FEN: &str = "r3k2r/pPp2ppp/8/3pP3/8/8/PPP2PPP/R3K2R w KQkq d6 0 1";
+        let move_gen = MoveGenerator::new();
+        let mut move_list = MoveList::new();
+        let mut board = Board::from_fen(FEN).unwrap();
+        let pawn_hash = board.pawn_hash();
+
+        move_gen.generate_legal_moves(&board, &mut move_list);
+        for mv in move_list.iter() {
+            board.make_move_unchecked(mv).unwrap();
+            // the incremental pawn hash matches one computed from scratch
+            let expected = Board::from_fen(&board.to_fen()).unwrap().pawn_hash();
+            assert_eq!(board.pawn_hash(), expected, "{}", mv.to_long_algebraic());
+
+            let touches_pawns = mv.piece() == Piece::Pawn
+                || mv.captured_piece() == Some(Piece::Pawn)
+                || mv.is_en_passant_capture();
+            assert_eq!(board.pawn_hash() != pawn_hash, touches_pawns);
+
+            board.unmake_move().unwrap();
+            assert_eq!(board.pawn_hash(), pawn_hash);
+        }
+    }
+
     #[test]
     fn make_move_updates_castling_rights() {
         // TODO
@@ -8,7 +8,7 @@ use std::fmt::Display;
 
 /// Represents the state of the board at a given point in time.
 /// This includes the half move clock, full move number, side to move,
-/// en passant square, castling rights, and the Zobrist hash.
+/// en passant square, castling rights, and the Zobrist hashes.
 ///
 /// This is used to restore the state in [`Board`] when un-making a move.
 #[derive(Debug, Clone, Copy)]
@@ -19,6 +19,8 @@ pub struct BoardState {
     pub en_passant_square: Option<u8>,
     pub castling_rights: u8,
     pub zobrist_hash: ZobristHash,
+    /// Zobrist hash of the pawns only.
+    pub pawn_hash: ZobristHash,
     pub next_move: Move,
 }
 
@@ -37,6 +39,7 @@ impl BoardState {
             en_passant_square: None,
             castling_rights: CastlingAvailability::NONE,
             zobrist_hash: 0,
+            pawn_hash: 0,
             next_move: Move::default(),
         }
     }
@@ -46,13 +49,14 @@ impl Display for BoardState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "state {{ half_move_clock: {}, full_move_number: {}, side_to_move: {:?}, en_passant_square: {:?}, castling_rights: {:?}, zobrist_hash: {}, next_move: {} }}",
+            "state {{ half_move_clock: {}, full_move_number: {}, side_to_move: {:?}, en_passant_square: {:?}, castling_rights: {:?}, zobrist_hash: {}, pawn_hash: {}, next_move: {} }}",
             self.half_move_clock,
             self.full_move_number,
             self.side_to_move,
             self.en_passant_square,
             self.castling_rights,
             self.zobrist_hash,
+            self.pawn_hash,
             self.next_move.to_long_algebraic()
         )
     }
@@ -72,13 +76,14 @@ mod tests {
         assert_eq!(board_state.en_passant_square, None);
         assert_eq!(board_state.castling_rights, CastlingAvailability::NONE);
         assert_eq!(board_state.zobrist_hash, 0);
+        assert_eq!(board_state.pawn_hash, 0);
         assert_eq!(board_state.next_move, Move::default());
     }
 
     #[test]
     fn display_board_state() {
         let board_state = BoardState::new();
-        let expected = "state { half_move_clock: 0, full_move_number: 1, side_to_move: White, en_passant_square: None, castling_rights: 0, zobrist_hash: 0, next_move: a1a1 }";
+        let expected = "state { half_move_clock: 0, full_move_number: 1, side_to_move: White, en_passant_square: None, castling_rights: 0, zobrist_hash: 0, pawn_hash: 0, next_move: a1a1 }";
         assert_eq!(board_state.to_string(), expected);
     }
 }
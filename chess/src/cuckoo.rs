@@ -0,0 +1,182 @@
+// Part of the byte-knight project.
+// Author: Paul Tsouchlos (ptsouchlos) (developer.paul.123@gmail.com)
+// GNU General Public License v3.0 or later
+// https://www.gnu.org/licenses/gpl-3.0-standalone.html
+
+//! Upcoming repetition detection using cuckoo tables, as described by Marcel van Kervinck in
+//! <https://web.archive.org/web/20201107002606/https://marcelk.net/2013-04-06/paper/upcoming-rep-v2.pdf>.
+//! Every reversible move of a piece from one square to another changes the Zobrist hash by the
+//! same amount. These hash differences are stored in a cuckoo table, so that a single lookup can
+//! tell if the current position is one move away from a position that was already played.
+
+use std::sync::LazyLock;
+
+use crate::{
+    attacks, bitboard::Bitboard, board::Board, definitions::NumberOf, pieces::Piece, rays,
+    side::Side, zobrist::ZobristRandomValues,
+};
+
+/// Number of entries in the cuckoo table. Must be a power of two.
+const TABLE_SIZE: usize = 8192;
+
+/// Pieces that can make reversible moves.
+const REVERSIBLE_PIECES: [Piece; 5] = [
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+    Piece::King,
+];
+
+static CUCKOO_TABLE: LazyLock<CuckooTable> = LazyLock::new(CuckooTable::new);
+
+/// Hash differences of all reversible moves and the squares of the move that causes them.
+struct CuckooTable {
+    keys: Vec<u64>,
+    moves: Vec<Option<(u8, u8)>>,
+}
+
+const fn first_index(key: u64) -> usize {
+    (key as usize) & (TABLE_SIZE - 1)
+}
+
+const fn second_index(key: u64) -> usize {
+    ((key >> 16) as usize) & (TABLE_SIZE - 1)
+}
+
+impl CuckooTable {
+    fn new() -> Self {
+        let mut table = Self {
+            keys: vec![0; TABLE_SIZE],
+            moves: vec![None; TABLE_SIZE],
+        };
+
+        // the random values are seeded, so they are the same as the values used by every board
+        let zobrist = ZobristRandomValues::new();
+        let side_change = zobrist.get_side_value(Side::White as usize)
+            ^ zobrist.get_side_value(Side::Black as usize);
+
+        for side in [Side::White, Side::Black] {
+            for piece in REVERSIBLE_PIECES {
+                let piece_value = |square: u8| {
+                    zobrist.get_piece_value(piece as usize, side as usize, square as usize)
+                };
+                for from in 0..NumberOf::SQUARES as u8 {
+                    for to in from + 1..NumberOf::SQUARES as u8 {
+                        if !attacks::for_piece(piece, from, Bitboard::default(), side)
+                            .is_square_occupied(to)
+                        {
+                            continue;
+                        }
+
+                        let key = piece_value(from) ^ piece_value(to) ^ side_change;
+                        table.insert(key, (from, to));
+                    }
+                }
+            }
+        }
+
+        table
+    }
+
+    /// Inserts a key, moving the keys already in the table to their other slot until every key
+    /// has a place.
+    fn insert(&mut self, mut key: u64, squares: (u8, u8)) {
+        let mut squares = Some(squares);
+        let mut index = first_index(key);
+        loop {
+            std::mem::swap(&mut self.keys[index], &mut key);
+            std::mem::swap(&mut self.moves[index], &mut squares);
+            if squares.is_none() {
+                break;
+            }
+            index = if index == first_index(key) {
+                second_index(key)
+            } else {
+                first_index(key)
+            };
+        }
+    }
+
+    /// Returns the squares of the reversible move that changes the hash by `key`, if there is one.
+    fn lookup(&self, key: u64) -> Option<(u8, u8)> {
+        [first_index(key), second_index(key)]
+            .into_iter()
+            .find(|&index| self.keys[index] == key)
+            .and_then(|index| self.moves[index])
+    }
+}
+
+impl Board {
+    /// Checks if the side to move can reach a position that was already played after the root
+    /// of a search, `ply` plies ago, with a single reversible move. Such a position can be
+    /// repeated, so the side to move can claim at least a draw.
+    pub fn upcoming_repetition(&self, ply: usize) -> bool {
+        let end = self.half_move_clock() as usize;
+        if end < 3 {
+            return false;
+        }
+
+        let hash = self.zobrist_hash();
+        let occupancy = self.all_pieces();
+        for (distance, previous_state) in self.history.iter().rev().enumerate().take(end) {
+            let distance = distance + 1;
+            // a null move can't be repeated with a real move
+            if previous_state.next_move.is_null_move() {
+                return false;
+            }
+            // only repetitions of positions after the root can be claimed during the search
+            if distance >= ply {
+                return false;
+            }
+            // positions a single move away have the other side to move, so they are an odd
+            // number of plies back
+            if distance < 3 || distance.is_multiple_of(2) {
+                continue;
+            }
+
+            if let Some((from, to)) = CUCKOO_TABLE.lookup(hash ^ previous_state.zobrist_hash)
+                && (rays::between(from, to) & occupancy).as_number() == 0
+            {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CUCKOO_TABLE;
+    use crate::board::Board;
+
+    #[test]
+    fn all_reversible_moves_are_stored() {
+        let stored = CUCKOO_TABLE
+            .moves
+            .iter()
+            .filter(|squares| squares.is_some())
+            .count();
+        assert_eq!(stored, 3668);
+    }
+
+    #[test]
+    fn upcoming_repetitions() {
+        let mut board = Board::default_board();
+        for mv in ["g1f3", "g8f6", "f3g1"] {
+            board.make_uci_move(mv).unwrap();
+        }
+        // black can play f6g8 to repeat the starting position
+        assert!(board.upcoming_repetition(4));
+        // but not if the starting position was before the root
+        assert!(!board.upcoming_repetition(3));
+
+        // a pawn move can't be undone
+        let mut board = Board::default_board();
+        for mv in ["g1f3", "e7e6", "f3g1"] {
+            board.make_uci_move(mv).unwrap();
+        }
+        assert!(!board.upcoming_repetition(4));
+    }
+}
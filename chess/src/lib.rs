@@ -13,6 +13,7 @@ pub mod bitboard_helpers;
 pub mod board;
 pub mod board_state;
 pub mod color;
+pub mod cuckoo;
 pub mod definitions;
 pub mod fen;
 pub mod file;
@@ -29,6 +30,7 @@ pub mod piece_category;
 pub mod pieces;
 pub mod rank;
 pub mod rays;
+pub mod see;
 pub mod side;
 pub mod slider_pieces;
 pub mod square;
@@ -60,6 +60,11 @@ impl MoveList {
         self.moves.clear();
     }
 
+    /// Keep only the moves for which `f` returns true, in their original order.
+    pub fn retain(&mut self, mut f: impl FnMut(&Move) -> bool) {
+        self.moves.retain(|mv| f(mv));
+    }
+
     pub fn as_slice(&self) -> &[Move] {
         self.moves.as_slice()
     }
@@ -101,6 +106,23 @@ mod tests {
         assert_eq!(move_list.len(), 2);
     }
 
+    #[test]
+    fn retain() {
+        let mut move_list = MoveList::new();
+        for to in [16, 17, 18] {
+            move_list.push(Move::new_king_move(
+                &Square::from_square_index(8),
+                &Square::from_square_index(to),
+                None,
+            ));
+        }
+
+        move_list.retain(|mv| mv.to() != 17);
+        assert_eq!(move_list.len(), 2);
+        assert_eq!(move_list.at(0).unwrap().to(), 16);
+        assert_eq!(move_list.at(1).unwrap().to(), 18);
+    }
+
     #[test]
     #[should_panic]
     fn push_with_overflow() {
@@ -144,6 +144,16 @@ impl Move {
         Self { move_info }
     }
 
+    /// Creates a [`Move`] from its raw bit representation, as returned by [`Move::to_bits`].
+    pub const fn from_bits(bits: u32) -> Self {
+        Self { move_info: bits }
+    }
+
+    /// Returns the raw bit representation of the move. Useful for storing moves in compact form.
+    pub const fn to_bits(&self) -> u32 {
+        self.move_info
+    }
+
     /// Checks if the underlying move information is valid (i.e. non-zero).
     pub fn is_valid(&self) -> bool {
         !self.is_null_move()
@@ -523,4 +533,24 @@ mod tests {
         assert_eq!(mv.from(), from.to_square_index());
         assert_eq!(mv.to(), to.to_square_index());
     }
+
+    #[test]
+    fn bits_round_trip() {
+        let from = Square::new(File::G, Rank::R7);
+        let to = Square::new(File::H, Rank::R8);
+        let mv = Move::new(
+            &from,
+            &to,
+            MoveDescriptor::None,
+            Piece::Pawn,
+            Some(Piece::Rook),
+            Some(Piece::Knight),
+        );
+
+        let round_trip = Move::from_bits(mv.to_bits());
+        assert_eq!(round_trip, mv);
+        assert_eq!(round_trip.captured_piece(), Some(Piece::Rook));
+        assert_eq!(round_trip.promotion_piece(), Some(Piece::Knight));
+        assert!(Move::from_bits(0).is_null_move());
+    }
 }
@@ -0,0 +1,248 @@
+// Part of the byte-knight project.
+// Author: Paul Tsouchlos (ptsouchlos) (developer.paul.123@gmail.com)
+// GNU General Public License v3.0 or later
+// https://www.gnu.org/licenses/gpl-3.0-standalone.html
+
+//! [Static Exchange Evaluation](https://www.chessprogramming.org/Static_Exchange_Evaluation) (SEE).
+//! Works out if a sequence of captures on a single square wins or loses material, without making
+//! any moves on the board.
+
+use crate::{
+    attacks, bitboard::Bitboard, board::Board, definitions::NumberOf, moves::Move, pieces::Piece,
+    side::Side,
+};
+
+/// Piece values used by SEE. Use [`Piece`] to index into this array.
+pub const SEE_PIECE_VALUES: [i32; NumberOf::PIECE_TYPES] = [0, 900, 500, 300, 300, 100];
+
+/// Attackers are tried from least to most valuable.
+const LEAST_VALUABLE_FIRST: [Piece; NumberOf::PIECE_TYPES] = [
+    Piece::Pawn,
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+    Piece::King,
+];
+
+const fn see_value(piece: Piece) -> i32 {
+    SEE_PIECE_VALUES[piece as usize]
+}
+
+/// Returns the lowest set bit of the bitboard as a bitboard.
+const fn lowest_bit(bitboard: Bitboard) -> Bitboard {
+    Bitboard::new(bitboard.as_number() & bitboard.as_number().wrapping_neg())
+}
+
+impl Board {
+    /// Returns a [`Bitboard`] of all pieces, of both sides, that attack `square` with the given
+    /// occupancy.
+    pub fn attackers_to(&self, square: u8, occupancy: Bitboard) -> Bitboard {
+        let bishops =
+            self.piece_kind_bitboard(Piece::Bishop) | self.piece_kind_bitboard(Piece::Queen);
+        let rooks = self.piece_kind_bitboard(Piece::Rook) | self.piece_kind_bitboard(Piece::Queen);
+
+        (attacks::pawn(square, Side::White) & *self.piece_bitboard(Piece::Pawn, Side::Black))
+            | (attacks::pawn(square, Side::Black) & *self.piece_bitboard(Piece::Pawn, Side::White))
+            | (attacks::knight(square) & self.piece_kind_bitboard(Piece::Knight))
+            | (attacks::bishop(square, occupancy) & bishops)
+            | (attacks::rook(square, occupancy) & rooks)
+            | (attacks::king(square) & self.piece_kind_bitboard(Piece::King))
+    }
+
+    /// Checks if the given move wins at least `threshold` material once all captures on the
+    /// target square have been played out. Sliding pieces that are uncovered by earlier captures
+    /// (x-rays) are taken into account.
+    ///
+    /// # Arguments
+    ///
+    /// - `mv` - The move to evaluate. This is expected to be a legal move for the side to move.
+    /// - `threshold` - The minimum material balance, in [`SEE_PIECE_VALUES`] units.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the exchange is worth at least `threshold`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chess::{board::Board, move_generation::MoveGenerator, move_list::MoveList};
+    ///
+    /// // the pawn on e5 is defended by the knight on d7
+    /// let board = Board::from_fen("4k3/3n4/8/4p3/8/8/4Q3/4K3 w - - 0 1").unwrap();
+    /// let mut move_list = MoveList::new();
+    /// MoveGenerator::new().generate_legal_moves(&board, &mut move_list);
+    /// let qxe5 = move_list.iter().find(|mv| mv.to_long_algebraic() == "e2e5").unwrap();
+    /// assert!(!board.see(qxe5, 0));
+    /// ```
+    pub fn see(&self, mv: &Move, threshold: i32) -> bool {
+        // castling can't lose material
+        if mv.is_castle() {
+            return 0 >= threshold;
+        }
+
+        let from = mv.from();
+        let to = mv.to();
+
+        // the piece that will be standing on the target square after the move
+        let mut next_victim = mv.promotion_piece().unwrap_or(mv.piece());
+
+        let mut balance = mv.captured_piece().map_or(0, see_value) - threshold;
+        if let Some(promotion) = mv.promotion_piece() {
+            balance += see_value(promotion) - see_value(Piece::Pawn);
+        }
+
+        // even if the piece is not recaptured we don't beat the threshold
+        if balance < 0 {
+            return false;
+        }
+
+        // even if the piece is recaptured for free we still beat the threshold
+        balance -= see_value(next_victim);
+        if balance >= 0 {
+            return true;
+        }
+
+        let bishops =
+            self.piece_kind_bitboard(Piece::Bishop) | self.piece_kind_bitboard(Piece::Queen);
+        let rooks = self.piece_kind_bitboard(Piece::Rook) | self.piece_kind_bitboard(Piece::Queen);
+
+        let mut occupied = self.all_pieces();
+        occupied.clear_square(from);
+        occupied.set_square(to);
+        if mv.is_en_passant_capture() {
+            let captured_square = if self.side_to_move().is_white() {
+                to - 8
+            } else {
+                to + 8
+            };
+            occupied.clear_square(captured_square);
+        }
+
+        let mut attackers = self.attackers_to(to, occupied) & occupied;
+        let mut side = Side::opposite(self.side_to_move());
+
+        loop {
+            let side_attackers = attackers & self.pieces(side);
+            if side_attackers == 0 {
+                break;
+            }
+
+            // capture with the least valuable piece
+            for piece in LEAST_VALUABLE_FIRST {
+                let piece_attackers = side_attackers & *self.piece_bitboard(piece, side);
+                if piece_attackers != 0 {
+                    next_victim = piece;
+                    occupied ^= lowest_bit(piece_attackers);
+                    break;
+                }
+            }
+
+            // the capture may have uncovered a slider behind it
+            if matches!(next_victim, Piece::Pawn | Piece::Bishop | Piece::Queen) {
+                attackers |= attacks::bishop(to, occupied) & bishops;
+            }
+            if matches!(next_victim, Piece::Rook | Piece::Queen) {
+                attackers |= attacks::rook(to, occupied) & rooks;
+            }
+            attackers &= occupied;
+
+            side = Side::opposite(side);
+            balance = -balance - 1 - see_value(next_victim);
+
+            if balance >= 0 {
+                // the king can't capture if the square is still defended
+                if next_victim == Piece::King && (attackers & self.pieces(side)) != 0 {
+                    side = Side::opposite(side);
+                }
+                break;
+            }
+        }
+
+        // the side that can no longer profitably capture loses the exchange
+        side != self.side_to_move()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::Board, move_generation::MoveGenerator, move_list::MoveList, moves::Move};
+
+    fn find_move(board: &Board, name: &str) -> Move {
+        let mut move_list = MoveList::new();
+        MoveGenerator::new().generate_legal_moves(board, &mut move_list);
+        *move_list
+            .iter()
+            .find(|mv| mv.to_long_algebraic() == name)
+            .unwrap()
+    }
+
+    /// Checks that the SEE value of the move is exactly `value`.
+    fn assert_see_value(fen: &str, mv: &str, value: i32) {
+        let board = Board::from_fen(fen).unwrap();
+        let mv = find_move(&board, mv);
+        assert!(board.see(&mv, value), "{fen} {mv} >= {value}");
+        assert!(!board.see(&mv, value + 1), "{fen} {mv} < {}", value + 1);
+    }
+
+    #[test]
+    fn undefended_capture() {
+        assert_see_value(
+            "1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1",
+            "e1e5",
+            100,
+        );
+    }
+
+    #[test]
+    fn capture_with_x_rays() {
+        // NxP, nxN, RxN, BxR, QxB, QxQ: white loses the knight for a pawn
+        assert_see_value(
+            "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+            "d3e5",
+            -200,
+        );
+    }
+
+    #[test]
+    fn equal_trade() {
+        assert_see_value(
+            "4R3/2r3p1/5bk1/1p1r3p/p2PR1P1/P1BK1P2/1P6/8 b - - 0 1",
+            "h5g4",
+            0,
+        );
+    }
+
+    #[test]
+    fn queen_takes_defended_pawn() {
+        assert_see_value("4k3/3n4/8/4p3/8/8/4Q3/4K3 w - - 0 1", "e2e5", -800);
+    }
+
+    #[test]
+    fn en_passant() {
+        assert_see_value("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 100);
+    }
+
+    #[test]
+    fn promotions() {
+        // undefended promotion wins a queen for a pawn
+        assert_see_value("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q", 800);
+        // the rook recaptures the new queen
+        assert_see_value("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q", -100);
+    }
+
+    #[test]
+    fn king_cannot_recapture_defended_piece() {
+        // kxR is illegal because the bishop defends the rook
+        assert_see_value("8/8/8/8/8/1k6/8/RB2K3 w - - 0 1", "a1a2", 0);
+        // without the bishop the king wins the rook
+        assert_see_value("8/8/8/8/8/1k6/8/R3K3 w - - 0 1", "a1a2", -500);
+    }
+
+    #[test]
+    fn quiet_moves() {
+        // moving to a square attacked by a pawn loses the piece
+        assert_see_value("4k3/8/3p4/8/3N4/8/8/4K3 w - - 0 1", "d4c6", 0);
+        assert_see_value("4k3/8/3p4/8/8/3N4/8/4K3 w - - 0 1", "d3e5", -300);
+    }
+}
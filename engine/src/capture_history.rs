@@ -0,0 +1,85 @@
+// Part of the byte-knight project.
+// Author: Paul Tsouchlos (ptsouchlos) (developer.paul.123@gmail.com)
+// GNU General Public License v3.0 or later
+// https://www.gnu.org/licenses/gpl-3.0-standalone.html
+
+use chess::{definitions::NumberOf, moves::Move};
+
+use crate::{history_table::apply_gravity, score::LargeScoreType};
+
+/// History of captures indexed by the moving piece, target square and captured piece. This is used
+/// to tell apart captures that have the same MVV/LVA score.
+pub(crate) struct CaptureHistory {
+    table: [[[LargeScoreType; NumberOf::PIECE_TYPES]; NumberOf::SQUARES]; NumberOf::PIECE_TYPES],
+}
+
+impl CaptureHistory {
+    pub(crate) fn new() -> Self {
+        Self {
+            table: [[[Default::default(); NumberOf::PIECE_TYPES]; NumberOf::SQUARES];
+                NumberOf::PIECE_TYPES],
+        }
+    }
+
+    /// Returns the history score of a capture. Moves that are not captures always score `0`.
+    pub(crate) fn get(&self, mv: &Move) -> LargeScoreType {
+        mv.captured_piece().map_or(0, |captured| {
+            self.table[mv.piece() as usize][mv.to() as usize][captured as usize]
+        })
+    }
+
+    /// Applies `bonus` to the history of a capture, using history gravity. Moves that are not
+    /// captures are ignored.
+    pub(crate) fn update(&mut self, mv: &Move, bonus: LargeScoreType) {
+        if let Some(captured) = mv.captured_piece() {
+            let entry = &mut self.table[mv.piece() as usize][mv.to() as usize][captured as usize];
+            *entry = apply_gravity(*entry, bonus);
+        }
+    }
+}
+
+impl Default for CaptureHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::{
+        definitions::Squares,
+        moves::{Move, MoveDescriptor},
+        pieces::Piece,
+        square::Square,
+    };
+
+    use super::CaptureHistory;
+
+    fn knight_move(captured: Option<Piece>) -> Move {
+        Move::new(
+            &Square::from_square_index(Squares::F3),
+            &Square::from_square_index(Squares::E5),
+            MoveDescriptor::None,
+            Piece::Knight,
+            captured,
+            None,
+        )
+    }
+
+    #[test]
+    fn store_and_read() {
+        let mut history = CaptureHistory::new();
+        let takes_pawn = knight_move(Some(Piece::Pawn));
+        let takes_bishop = knight_move(Some(Piece::Bishop));
+
+        history.update(&takes_pawn, 200);
+        history.update(&takes_bishop, -50);
+        assert_eq!(history.get(&takes_pawn), 200);
+        assert_eq!(history.get(&takes_bishop), -50);
+
+        // quiet moves have no capture history
+        let quiet = knight_move(None);
+        history.update(&quiet, 300);
+        assert_eq!(history.get(&quiet), 0);
+    }
+}
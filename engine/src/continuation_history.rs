@@ -0,0 +1,127 @@
+// Part of the byte-knight project.
+// Author: Paul Tsouchlos (ptsouchlos) (developer.paul.123@gmail.com)
+// GNU General Public License v3.0 or later
+// https://www.gnu.org/licenses/gpl-3.0-standalone.html
+
+use chess::{definitions::NumberOf, moves::Move, pieces::Piece, side::Side};
+
+use crate::{history_table::apply_gravity, score::LargeScoreType};
+
+const TABLE_SIZE: usize = NumberOf::SIDES
+    * NumberOf::PIECE_TYPES
+    * NumberOf::SQUARES
+    * NumberOf::PIECE_TYPES
+    * NumberOf::SQUARES;
+
+/// History of quiet moves indexed by an earlier move in the same line, its piece and target
+/// square, followed by the piece and target square of the quiet move.
+///
+/// One table is used for each distance to the earlier move, e.g. the previous move (1-ply) or the
+/// move before that (2-ply, our own previous move).
+/// See <https://www.chessprogramming.org/History_Heuristic#History_Extensions>
+pub(crate) struct ContinuationHistory {
+    table: Vec<LargeScoreType>,
+}
+
+impl ContinuationHistory {
+    pub(crate) fn new() -> Self {
+        Self {
+            table: vec![Default::default(); TABLE_SIZE],
+        }
+    }
+
+    fn index(side: Side, previous: &Move, piece: Piece, square: u8) -> usize {
+        let previous_index = (side as usize * NumberOf::PIECE_TYPES + previous.piece() as usize)
+            * NumberOf::SQUARES
+            + previous.to() as usize;
+        (previous_index * NumberOf::PIECE_TYPES + piece as usize) * NumberOf::SQUARES
+            + square as usize
+    }
+
+    /// Returns the score of `side` moving `piece` to `square` after the `previous` move.
+    pub(crate) fn get(
+        &self,
+        side: Side,
+        previous: &Move,
+        piece: Piece,
+        square: u8,
+    ) -> LargeScoreType {
+        self.table[Self::index(side, previous, piece, square)]
+    }
+
+    /// Updates the score of `side` moving `piece` to `square` after the `previous` move, using the
+    /// same gravity formula as the main [`HistoryTable`](crate::history_table::HistoryTable).
+    pub(crate) fn update(
+        &mut self,
+        side: Side,
+        previous: &Move,
+        piece: Piece,
+        square: u8,
+        bonus: LargeScoreType,
+    ) {
+        let entry = &mut self.table[Self::index(side, previous, piece, square)];
+        *entry = apply_gravity(*entry, bonus);
+    }
+}
+
+impl Default for ContinuationHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::{
+        definitions::Squares,
+        moves::{Move, MoveDescriptor},
+        pieces::Piece,
+        side::Side,
+        square::Square,
+    };
+
+    use super::ContinuationHistory;
+    use crate::score::Score;
+
+    #[test]
+    fn store_and_read() {
+        let mut history = ContinuationHistory::new();
+        let previous = Move::new(
+            &Square::from_square_index(Squares::E2),
+            &Square::from_square_index(Squares::E4),
+            MoveDescriptor::PawnTwoUp,
+            Piece::Pawn,
+            None,
+            None,
+        );
+
+        history.update(Side::Black, &previous, Piece::Knight, Squares::F6, 100);
+        assert_eq!(
+            history.get(Side::Black, &previous, Piece::Knight, Squares::F6),
+            100
+        );
+        // only the exact combination is updated
+        assert_eq!(
+            history.get(Side::White, &previous, Piece::Knight, Squares::F6),
+            0
+        );
+        assert_eq!(
+            history.get(Side::Black, &previous, Piece::Knight, Squares::C6),
+            0
+        );
+
+        // gravity keeps the value bounded
+        for _ in 0..100 {
+            history.update(
+                Side::Black,
+                &previous,
+                Piece::Knight,
+                Squares::F6,
+                Score::MAX_HISTORY,
+            );
+        }
+        assert!(
+            history.get(Side::Black, &previous, Piece::Knight, Squares::F6) <= Score::MAX_HISTORY
+        );
+    }
+}
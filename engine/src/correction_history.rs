@@ -0,0 +1,168 @@
+// Part of the byte-knight project.
+// Author: Paul Tsouchlos (ptsouchlos) (developer.paul.123@gmail.com)
+// GNU General Public License v3.0 or later
+// https://www.gnu.org/licenses/gpl-3.0-standalone.html
+
+use chess::{board::Board, definitions::NumberOf, pieces::Piece, side::Side};
+
+use crate::{
+    score::{LargeScoreType, Score, ScoreType},
+    tuneable::{
+        CORRHIST_GRAIN, CORRHIST_MAX_CORRECTION, CORRHIST_MAX_WEIGHT, CORRHIST_WEIGHT_SCALE,
+    },
+};
+
+/// Number of entries for each side in a correction table. Must be a power of two.
+const TABLE_SIZE: usize = 16_384;
+
+/// Pieces counted by the material key, kings are always on the board.
+const MATERIAL_PIECES: [Piece; 5] = [
+    Piece::Queen,
+    Piece::Rook,
+    Piece::Bishop,
+    Piece::Knight,
+    Piece::Pawn,
+];
+
+/// Correction history learns how far the static evaluation is from the search result for similar
+/// positions, and uses that to correct the static evaluation of new positions. Positions are
+/// grouped by their pawn structure and by their material.
+/// See <https://www.chessprogramming.org/Static_Evaluation_Correction_History>
+pub struct CorrectionHistory {
+    pawn: Vec<LargeScoreType>,
+    material: Vec<LargeScoreType>,
+}
+
+impl CorrectionHistory {
+    pub(crate) fn new() -> Self {
+        Self {
+            pawn: vec![Default::default(); NumberOf::SIDES * TABLE_SIZE],
+            material: vec![Default::default(); NumberOf::SIDES * TABLE_SIZE],
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.pawn.fill(Default::default());
+        self.material.fill(Default::default());
+    }
+
+    fn index(side: Side, key: u64) -> usize {
+        side as usize * TABLE_SIZE + (key as usize & (TABLE_SIZE - 1))
+    }
+
+    /// Returns a key that is the same for all positions with the same material.
+    fn material_key(board: &Board) -> u64 {
+        let counts = [Side::White, Side::Black]
+            .iter()
+            .flat_map(|side| {
+                MATERIAL_PIECES.iter().map(|piece| {
+                    board
+                        .piece_bitboard(*piece, *side)
+                        .number_of_occupied_squares()
+                })
+            })
+            .fold(0u64, |key, count| key << 4 | u64::from(count.min(15)));
+
+        // spread the counts over the whole key so they don't all land in the lowest bits
+        counts.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32
+    }
+
+    /// Returns the static evaluation `eval` of `board` corrected by the learned error.
+    pub(crate) fn correct(&self, board: &Board, eval: Score) -> Score {
+        let stm = board.side_to_move();
+        let pawn = self.pawn[Self::index(stm, board.pawn_hash())];
+        let material = self.material[Self::index(stm, Self::material_key(board))];
+        let correction = (pawn + material) / (2 * CORRHIST_GRAIN);
+
+        // never turn a normal evaluation into a mate score
+        let bound = LargeScoreType::from(Score::MINIMUM_MATE.0) - 1;
+        let corrected = (LargeScoreType::from(eval.0) + correction).clamp(-bound, bound);
+        Score::new(corrected as ScoreType)
+    }
+
+    /// Learns that a search of `board` to `depth` differed from its static evaluation by `error`.
+    /// Deeper searches are given more weight.
+    pub(crate) fn update(&mut self, board: &Board, depth: ScoreType, error: LargeScoreType) {
+        let stm = board.side_to_move();
+        let weight = (LargeScoreType::from(depth) + 1).min(CORRHIST_MAX_WEIGHT);
+        let target =
+            error.clamp(-CORRHIST_MAX_CORRECTION, CORRHIST_MAX_CORRECTION) * CORRHIST_GRAIN;
+
+        let pawn_index = Self::index(stm, board.pawn_hash());
+        let material_index = Self::index(stm, Self::material_key(board));
+        for entry in [
+            &mut self.pawn[pawn_index],
+            &mut self.material[material_index],
+        ] {
+            *entry = (*entry * (CORRHIST_WEIGHT_SCALE - weight) + target * weight)
+                / CORRHIST_WEIGHT_SCALE;
+        }
+    }
+}
+
+impl Default for CorrectionHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::board::Board;
+
+    use super::CorrectionHistory;
+    use crate::{
+        score::{Score, ScoreType},
+        tuneable::CORRHIST_MAX_CORRECTION,
+    };
+
+    #[test]
+    fn corrections_move_towards_the_error() {
+        let mut history = CorrectionHistory::new();
+        let board = Board::from_fen("4k3/pp6/8/8/8/8/PPP5/4K3 w - - 0 1").unwrap();
+        let eval = Score::new(100);
+        assert_eq!(history.correct(&board, eval), eval);
+
+        history.update(&board, 10, 50);
+        let once = history.correct(&board, eval);
+        assert!(once > eval && once < Score::new(150));
+
+        for _ in 0..200 {
+            history.update(&board, 10, 50);
+        }
+        let corrected = history.correct(&board, eval);
+        assert!(corrected > once && corrected <= Score::new(150));
+
+        // the other side to move has its own corrections
+        let black = Board::from_fen("4k3/pp6/8/8/8/8/PPP5/4K3 b - - 0 1").unwrap();
+        assert_eq!(history.correct(&black, eval), eval);
+    }
+
+    #[test]
+    fn corrections_are_limited() {
+        let mut history = CorrectionHistory::new();
+        let board = Board::from_fen("4k3/pp6/8/8/8/8/PPP5/4K3 w - - 0 1").unwrap();
+        for _ in 0..500 {
+            history.update(&board, 20, 10_000);
+        }
+        assert!(
+            history.correct(&board, Score::DRAW)
+                <= Score::new(CORRHIST_MAX_CORRECTION as ScoreType)
+        );
+    }
+
+    #[test]
+    fn material_keys() {
+        let board = Board::from_fen("4k3/pp6/8/8/8/8/PPP5/4K3 w - - 0 1").unwrap();
+        let moved = Board::from_fen("4k3/p7/1p6/8/8/8/PPP5/4K3 w - - 0 1").unwrap();
+        let fewer = Board::from_fen("4k3/p7/8/8/8/8/PPP5/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            CorrectionHistory::material_key(&board),
+            CorrectionHistory::material_key(&moved)
+        );
+        assert_ne!(
+            CorrectionHistory::material_key(&board),
+            CorrectionHistory::material_key(&fewer)
+        );
+    }
+}
@@ -0,0 +1,93 @@
+// Part of the byte-knight project.
+// Author: Paul Tsouchlos (ptsouchlos) (developer.paul.123@gmail.com)
+// GNU General Public License v3.0 or later
+// https://www.gnu.org/licenses/gpl-3.0-standalone.html
+
+use chess::{definitions::NumberOf, moves::Move, pieces::Piece, side::Side};
+
+/// Stores the quiet move that last refuted a given move, indexed by the side, piece and target
+/// square of the move being refuted.
+/// See <https://www.chessprogramming.org/Countermove_Heuristic>
+pub(crate) struct CountermoveTable {
+    table: [[[Option<Move>; NumberOf::SQUARES]; NumberOf::PIECE_TYPES]; NumberOf::SIDES],
+}
+
+impl CountermoveTable {
+    pub(crate) fn new() -> Self {
+        Self {
+            table: [[[None; NumberOf::SQUARES]; NumberOf::PIECE_TYPES]; NumberOf::SIDES],
+        }
+    }
+
+    /// Returns the countermove for the previous move, made by `side` with `piece` to `square`.
+    pub(crate) fn get(&self, side: Side, piece: Piece, square: u8) -> Option<Move> {
+        self.table[side as usize][piece as usize][square as usize]
+    }
+
+    pub(crate) fn update(&mut self, side: Side, piece: Piece, square: u8, mv: Move) {
+        self.table[side as usize][piece as usize][square as usize] = Some(mv);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.table
+            .iter_mut()
+            .flatten()
+            .for_each(|squares| *squares = [None; NumberOf::SQUARES]);
+    }
+}
+
+impl Default for CountermoveTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::{
+        definitions::Squares,
+        moves::{Move, MoveDescriptor},
+        pieces::Piece,
+        side::Side,
+        square::Square,
+    };
+
+    use super::CountermoveTable;
+
+    #[test]
+    fn store_and_read() {
+        let mut countermoves = CountermoveTable::new();
+        let reply = Move::new(
+            &Square::from_square_index(Squares::G8),
+            &Square::from_square_index(Squares::F6),
+            MoveDescriptor::None,
+            Piece::Knight,
+            None,
+            None,
+        );
+
+        assert!(
+            countermoves
+                .get(Side::White, Piece::Pawn, Squares::E4)
+                .is_none()
+        );
+        countermoves.update(Side::White, Piece::Pawn, Squares::E4, reply);
+        assert_eq!(
+            countermoves.get(Side::White, Piece::Pawn, Squares::E4),
+            Some(reply)
+        );
+        // the same move by the other side is a different entry
+        assert!(
+            countermoves
+                .get(Side::Black, Piece::Pawn, Squares::E4)
+                .is_none()
+        );
+
+        countermoves.clear();
+        assert!(
+            countermoves
+                .get(Side::White, Piece::Pawn, Squares::E4)
+                .is_none()
+        );
+    }
+}
@@ -5,7 +5,11 @@
 
 use std::{
     io::{self, Write},
-    sync::{Arc, Mutex},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
 };
 
 use chess::board::Board;
@@ -13,19 +17,30 @@ use uci_parser::{UciCommand, UciInfo, UciOption, UciResponse};
 
 use crate::{
     defs::About,
-    history_table::HistoryTable,
     input_handler::{CommandProxy, EngineCommand, InputHandler},
     log_level::{LogDebug, LogInfo, LogLevel},
-    search::SearchParameters,
-    search_thread::SearchThread,
+    score::ScoreType,
+    search::{self, SearchParameters},
+    search_thread::{self, SearchThread, ThreadHistory},
+    time_manager,
     ttable::{self, TranspositionTable},
 };
 
 pub struct ByteKnight {
     input_handler: InputHandler,
     search_thread: SearchThread,
-    transposition_table: Arc<Mutex<TranspositionTable>>,
-    history_table: Arc<Mutex<HistoryTable>>,
+    transposition_table: Arc<TranspositionTable>,
+    /// History tables of every search thread, kept between searches. The first one belongs to
+    /// the main thread.
+    histories: Arc<Mutex<Vec<ThreadHistory>>>,
+    /// Draw score offset set with the `Contempt` option, see [`SearchParameters::contempt`].
+    contempt: ScoreType,
+    /// Number of lines to report, set with the `MultiPV` option.
+    multi_pv: usize,
+    /// Time kept back from the clock for every move, set with the `Move Overhead` option.
+    move_overhead: Duration,
+    /// Flag of the current `go ponder` search, cleared on `ponderhit`.
+    ponder_flag: Option<Arc<AtomicBool>>,
     debug: bool,
 }
 
@@ -35,18 +50,20 @@ impl ByteKnight {
             input_handler: InputHandler::new(),
             search_thread: SearchThread::new(),
             transposition_table: Default::default(),
-            history_table: Default::default(),
+            histories: Arc::new(Mutex::new(vec![ThreadHistory::default()])),
+            contempt: 0,
+            multi_pv: 1,
+            move_overhead: Duration::from_millis(time_manager::DEFAULT_MOVE_OVERHEAD_MS),
+            ponder_flag: None,
             debug: false,
         }
     }
 
     fn clear_hash_tables(&mut self) {
-        if let Ok(tt) = self.transposition_table.lock().as_mut() {
-            tt.clear();
-        }
+        self.transposition_table.clear();
 
-        if let Ok(ht) = self.history_table.lock().as_mut() {
-            ht.clear();
+        if let Ok(histories) = self.histories.lock().as_mut() {
+            histories.iter_mut().for_each(|history| history.clear());
         }
     }
 
@@ -86,7 +103,31 @@ impl ByteKnight {
 
                         let options = vec![
                             UciOption::<&str, i32>::spin("Hash", 16, 1, 1024),
-                            UciOption::<&str, i32>::spin("Threads", 1, 1, 1),
+                            UciOption::<&str, i32>::spin(
+                                "Threads",
+                                1,
+                                1,
+                                search_thread::MAX_THREADS as i32,
+                            ),
+                            UciOption::<&str, i32>::check("Ponder", false),
+                            UciOption::<&str, i32>::spin(
+                                "MultiPV",
+                                1,
+                                1,
+                                search::MAX_MULTI_PV as i32,
+                            ),
+                            UciOption::<&str, i32>::spin(
+                                "Contempt",
+                                0,
+                                -search::MAX_CONTEMPT as i32,
+                                search::MAX_CONTEMPT as i32,
+                            ),
+                            UciOption::<&str, i32>::spin(
+                                "Move Overhead",
+                                time_manager::DEFAULT_MOVE_OVERHEAD_MS as i32,
+                                0,
+                                time_manager::MAX_MOVE_OVERHEAD_MS as i32,
+                            ),
                         ];
 
                         for option in options {
@@ -99,6 +140,7 @@ impl ByteKnight {
                     UciCommand::UciNewGame => {
                         board = Board::default_board();
                         self.clear_hash_tables();
+                        self.transposition_table.new_search();
                     }
                     UciCommand::Position { fen, moves } => {
                         match fen {
@@ -124,8 +166,15 @@ impl ByteKnight {
                             UciInfo::default().string(format!("searching {}", board.to_fen()));
                         writeln!(stdout, "{}", UciResponse::info(info)).unwrap();
 
+                        // entries from previous searches should be replaced first
+                        self.transposition_table.new_search();
+
                         // create the search parameters
-                        let search_params = SearchParameters::new(search_options, &board);
+                        let mut search_params =
+                            SearchParameters::new(search_options, &board, self.move_overhead);
+                        search_params.contempt = self.contempt;
+                        search_params.multi_pv = self.multi_pv;
+                        self.ponder_flag = search_params.ponder.clone();
                         if self.debug {
                             self.start_search::<LogDebug>(board.clone(), search_params);
                         } else {
@@ -152,35 +201,97 @@ impl ByteKnight {
                                     continue;
                                 }
 
-                                self.transposition_table = Arc::new(Mutex::new(
-                                    TranspositionTable::from_size_in_mb(hash_size),
-                                ));
+                                self.transposition_table =
+                                    Arc::new(TranspositionTable::from_size_in_mb(hash_size));
+                            }
+                        } else if name.to_lowercase() == "threads"
+                            && let Some(val) = value
+                            && let Ok(threads) = val.parse::<usize>()
+                        {
+                            if !(1..=search_thread::MAX_THREADS).contains(&threads) {
+                                eprintln!(
+                                    "Invalid number of threads. Must be between 1 and {}",
+                                    search_thread::MAX_THREADS
+                                );
+                                continue;
+                            }
+
+                            if let Ok(histories) = self.histories.lock().as_mut() {
+                                histories.resize_with(threads, ThreadHistory::default);
+                            }
+                        } else if name.to_lowercase() == "contempt"
+                            && let Some(val) = value
+                            && let Ok(contempt) = val.parse::<ScoreType>()
+                        {
+                            if !(-search::MAX_CONTEMPT..=search::MAX_CONTEMPT).contains(&contempt) {
+                                eprintln!(
+                                    "Invalid contempt. Must be between {} and {}",
+                                    -search::MAX_CONTEMPT,
+                                    search::MAX_CONTEMPT
+                                );
+                                continue;
                             }
+
+                            self.contempt = contempt;
+                        } else if name.to_lowercase() == "multipv"
+                            && let Some(val) = value
+                            && let Ok(multi_pv) = val.parse::<usize>()
+                        {
+                            if !(1..=search::MAX_MULTI_PV).contains(&multi_pv) {
+                                eprintln!(
+                                    "Invalid MultiPV. Must be between 1 and {}",
+                                    search::MAX_MULTI_PV
+                                );
+                                continue;
+                            }
+
+                            self.multi_pv = multi_pv;
+                        } else if name.to_lowercase() == "move overhead"
+                            && let Some(val) = value
+                            && let Ok(move_overhead) = val.parse::<u64>()
+                        {
+                            if move_overhead > time_manager::MAX_MOVE_OVERHEAD_MS {
+                                eprintln!(
+                                    "Invalid move overhead. Must be between 0 and {} ms",
+                                    time_manager::MAX_MOVE_OVERHEAD_MS
+                                );
+                                continue;
+                            }
+
+                            self.move_overhead = Duration::from_millis(move_overhead);
                         }
                     }
                     UciCommand::Stop => {
                         self.search_thread.stop_search();
                     }
+                    UciCommand::PonderHit => {
+                        // the opponent played the move we pondered on, so this is now a normal search
+                        if let Some(ponder_flag) = self.ponder_flag.take() {
+                            ponder_flag.store(false, Ordering::Relaxed);
+                            self.search_thread.wake();
+                        }
+                    }
                     _ => {}
                 },
                 CommandProxy::Engine(engine_command) => match engine_command {
                     EngineCommand::HashInfo => {
-                        if let Ok(tt) = self.transposition_table.lock() {
-                            writeln!(
-                                stdout,
-                                "full: {:.2}% hits: {} access: {} collisions: {} cap: {}",
-                                tt.fullness(),
-                                tt.hits,
-                                tt.accesses,
-                                tt.collisions,
-                                tt.size(),
-                            )
-                            .unwrap();
-                        }
+                        let tt = &self.transposition_table;
+                        writeln!(
+                            stdout,
+                            "full: {:.2}% hits: {} access: {} collisions: {} cap: {}",
+                            tt.fullness(),
+                            tt.hits(),
+                            tt.accesses(),
+                            tt.collisions(),
+                            tt.size(),
+                        )
+                        .unwrap();
                     }
                     EngineCommand::History => {
-                        if let Ok(ht) = self.history_table.lock() {
-                            ht.print_for_side(board.side_to_move());
+                        if let Ok(histories) = self.histories.lock()
+                            && let Some(history) = histories.first()
+                        {
+                            history.history_table.print_for_side(board.side_to_move());
                         }
                     }
                     EngineCommand::Perft(depth) => {
@@ -202,7 +313,7 @@ impl ByteKnight {
             &board,
             params,
             Arc::clone(&self.transposition_table),
-            Arc::clone(&self.history_table),
+            Arc::clone(&self.histories),
         );
     }
 }
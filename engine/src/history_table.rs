@@ -31,6 +31,16 @@ pub(crate) fn calculate_bonus_for_depth(depth: i16) -> i16 {
         .saturating_sub(Score::HISTORY_OFFSET)
 }
 
+/// Applies a bonus to a history value using [history gravity](https://www.chessprogramming.org/History_Heuristic),
+/// which keeps the value within `[-Score::MAX_HISTORY, Score::MAX_HISTORY]`.
+pub(crate) fn apply_gravity(
+    current_value: LargeScoreType,
+    bonus: LargeScoreType,
+) -> LargeScoreType {
+    let clamped_bonus = bonus.clamp(-Score::MAX_HISTORY, Score::MAX_HISTORY);
+    current_value + clamped_bonus - current_value * clamped_bonus.abs() / Score::MAX_HISTORY
+}
+
 impl HistoryTable {
     pub(crate) fn new() -> Self {
         let table =
@@ -43,11 +53,8 @@ impl HistoryTable {
     }
 
     pub(crate) fn update(&mut self, side: Side, piece: Piece, square: u8, bonus: LargeScoreType) {
-        let current_value = self.table[side as usize][piece as usize][square as usize];
-        let clamped_bonus = bonus.clamp(-Score::MAX_HISTORY, Score::MAX_HISTORY);
-        let new_value = current_value + clamped_bonus
-            - current_value * clamped_bonus.abs() / Score::MAX_HISTORY;
-        self.table[side as usize][piece as usize][square as usize] = new_value;
+        let entry = &mut self.table[side as usize][piece as usize][square as usize];
+        *entry = apply_gravity(*entry, bonus);
     }
 
     pub(crate) fn clear(&mut self) {
@@ -6,7 +6,8 @@
 use crate::move_order::MoveOrder;
 use chess::moves::Move;
 
-/// Iterator type that yields moves in a sorted order based on their move orders.
+/// Iterator type that yields moves, together with their move order, in a sorted order based on
+/// their move orders.
 /// The moves are sorted in-place, meaning that the original array of moves is modified.
 /// The sorting is done in a way that the highest scoring moves are returned first.
 /// The iterator is stateful and keeps track of the current index in the moves array.
@@ -46,7 +47,7 @@ impl<'s> InplaceIncrementalSort<'s> {
         self.current_index < self.moves.len()
     }
 
-    pub(crate) fn next(&mut self) -> Option<Move> {
+    pub(crate) fn next(&mut self) -> Option<(Move, MoveOrder)> {
         if !self.has_next() {
             return None;
         }
@@ -72,12 +73,12 @@ impl<'s> InplaceIncrementalSort<'s> {
         self.current_index += 1;
 
         // Take the item at the current index
-        Some(self.moves[current])
+        Some((self.moves[current], self.move_order[current]))
     }
 }
 
 impl Iterator for InplaceIncrementalSort<'_> {
-    type Item = Move;
+    type Item = (Move, MoveOrder);
 
     fn next(&mut self) -> Option<Self::Item> {
         self.next()
@@ -117,15 +118,15 @@ mod tests {
 
         let mut order = vec![
             MoveOrder::Quiet(14),
-            MoveOrder::Capture(Piece::Bishop, Piece::Rook),
-            MoveOrder::Capture(Piece::Queen, Piece::Pawn),
+            MoveOrder::Capture(Piece::Bishop, Piece::Rook, 0),
+            MoveOrder::Capture(Piece::Queen, Piece::Pawn, 0),
             MoveOrder::TtMove,
             MoveOrder::Quiet(15),
             MoveOrder::Quiet(16),
             MoveOrder::Quiet(17),
             MoveOrder::Quiet(100),
-            MoveOrder::Capture(Piece::Pawn, Piece::Queen),
-            MoveOrder::Capture(Piece::Knight, Piece::Bishop),
+            MoveOrder::Capture(Piece::Pawn, Piece::Queen, 0),
+            MoveOrder::Capture(Piece::Knight, Piece::Bishop, 0),
             MoveOrder::Quiet(4),
             MoveOrder::Quiet(24),
             MoveOrder::Quiet(43),
@@ -162,7 +163,7 @@ mod tests {
         ];
 
         // loop through the iterator
-        for (i, mv) in incremental_sort.enumerate() {
+        for (i, (mv, _)) in incremental_sort.enumerate() {
             println!("{}: {}", i, mv.to_long_algebraic());
             assert_eq!(mv.to_long_algebraic(), expected_moves[i]);
         }
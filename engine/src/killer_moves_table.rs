@@ -0,0 +1,79 @@
+// Part of the byte-knight project.
+// Author: Paul Tsouchlos (ptsouchlos) (developer.paul.123@gmail.com)
+// GNU General Public License v3.0 or later
+// https://www.gnu.org/licenses/gpl-3.0-standalone.html
+
+use chess::moves::Move;
+
+/// Number of killer moves stored for each ply.
+pub(crate) const KILLER_MOVES_PER_PLY: usize = 2;
+
+/// Killer moves are quiet moves that caused a beta cutoff at the same ply in a sibling node.
+/// They are likely to cause a cutoff again, so they are searched before the other quiet moves.
+/// Every entry of the search stack keeps the killer moves of its ply.
+/// See <https://www.chessprogramming.org/Killer_Heuristic>
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct KillerMoves {
+    moves: [Option<Move>; KILLER_MOVES_PER_PLY],
+}
+
+impl KillerMoves {
+    /// Returns the killer moves, most recent first.
+    pub(crate) fn as_slice(&self) -> &[Option<Move>] {
+        self.moves.as_slice()
+    }
+
+    /// Stores a new killer move, pushing out the oldest one.
+    pub(crate) fn update(&mut self, mv: Move) {
+        // don't fill every slot with the same move
+        if self.moves[0] == Some(mv) {
+            return;
+        }
+
+        self.moves.rotate_right(1);
+        self.moves[0] = Some(mv);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::{
+        moves::{Move, MoveDescriptor},
+        pieces::Piece,
+        square::Square,
+    };
+
+    use super::KillerMoves;
+
+    fn quiet_move(from: u8, to: u8) -> Move {
+        Move::new(
+            &Square::from_square_index(from),
+            &Square::from_square_index(to),
+            MoveDescriptor::None,
+            Piece::Knight,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn most_recent_killer_comes_first() {
+        let mut killers = KillerMoves::default();
+        let first = quiet_move(1, 18);
+        let second = quiet_move(6, 21);
+        let third = quiet_move(18, 35);
+
+        killers.update(first);
+        assert_eq!(killers.as_slice(), &[Some(first), None]);
+
+        killers.update(second);
+        assert_eq!(killers.as_slice(), &[Some(second), Some(first)]);
+
+        // storing the same move twice doesn't push out the other killer
+        killers.update(second);
+        assert_eq!(killers.as_slice(), &[Some(second), Some(first)]);
+
+        killers.update(third);
+        assert_eq!(killers.as_slice(), &[Some(third), Some(second)]);
+    }
+}
@@ -8,6 +8,10 @@
 #![deny(clippy::expect_used)]
 
 pub mod aspiration_window;
+pub(crate) mod capture_history;
+pub(crate) mod continuation_history;
+pub mod correction_history;
+pub(crate) mod countermove_table;
 pub mod defs;
 pub mod engine;
 pub mod evaluation;
@@ -15,6 +19,7 @@ pub mod hce_values;
 pub mod history_table;
 mod inplace_incremental_sort;
 pub mod input_handler;
+pub(crate) mod killer_moves_table;
 mod lmr;
 pub mod log_level;
 mod move_order;
@@ -22,10 +27,13 @@ pub(crate) mod node_types;
 pub mod pawn_structure;
 pub mod phased_score;
 pub(crate) mod principle_variation;
+pub(crate) mod root_moves;
 pub mod score;
 pub mod search;
+pub(crate) mod search_stack;
 pub mod search_thread;
 pub(crate) mod table;
+pub(crate) mod time_manager;
 pub mod traits;
 pub mod ttable;
 pub mod tuneable;
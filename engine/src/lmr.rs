@@ -3,7 +3,9 @@
 // GNU General Public License v3.0 or later
 // https://www.gnu.org/licenses/gpl-3.0-standalone.html
 
-use crate::tuneable::{LMR_OFFSET, LMR_SCALING_FACTOR};
+use crate::tuneable::{
+    LMR_CAPTURE_OFFSET, LMR_CAPTURE_SCALING_FACTOR, LMR_OFFSET, LMR_SCALING_FACTOR,
+};
 
 /// LMR (Late Move Reduction) formula for calculating the reduction factor
 /// based on the natural logarithm of the current depth and the number of moves made.
@@ -17,10 +19,34 @@ use crate::tuneable::{LMR_OFFSET, LMR_SCALING_FACTOR};
 ///
 /// A floating-point value representing the reduction factor.
 pub(crate) fn formula(depth: usize, move_count: usize) -> f64 {
+    log_formula(depth, move_count, LMR_OFFSET, LMR_SCALING_FACTOR)
+}
+
+/// LMR formula for captures. Captures are more likely to be good moves than quiets, so they are
+/// reduced less.
+///
+/// # Arguments
+///
+/// - `depth` - The current depth in the search tree.
+/// - `move_count` - The number of moves made so far.
+///
+/// # Returns
+///
+/// A floating-point value representing the reduction factor.
+pub(crate) fn capture_formula(depth: usize, move_count: usize) -> f64 {
+    log_formula(
+        depth,
+        move_count,
+        LMR_CAPTURE_OFFSET,
+        LMR_CAPTURE_SCALING_FACTOR,
+    )
+}
+
+fn log_formula(depth: usize, move_count: usize, offset: f64, scaling_factor: f64) -> f64 {
     let d_ln = (depth as f64).ln();
     let mvs_ln = (move_count as f64).ln();
     if d_ln.is_finite() && mvs_ln.is_finite() {
-        LMR_OFFSET + (d_ln * mvs_ln) / LMR_SCALING_FACTOR
+        offset + (d_ln * mvs_ln) / scaling_factor
     } else {
         0_f64
     }
@@ -48,6 +74,15 @@ mod tests {
         assert_eq!(result, ZERO);
     }
 
+    #[test]
+    fn test_captures_are_reduced_less() {
+        for depth in 2..64 {
+            for move_count in 2..64 {
+                assert!(capture_formula(depth, move_count) < formula(depth, move_count));
+            }
+        }
+    }
+
     #[test]
     fn test_lmr_formula_zero_moves() {
         let depth = 5;
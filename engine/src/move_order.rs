@@ -7,18 +7,26 @@ use std::cmp::Ordering;
 
 use anyhow::{Ok, Result};
 use arrayvec::ArrayVec;
-use chess::{definitions::MAX_MOVE_LIST_SIZE, moves::Move, pieces::Piece, side::Side};
-
-use crate::{
-    evaluation::Evaluation, hce_values::ByteKnightValues, history_table, score::LargeScoreType,
+use chess::{
+    attacks, bitboard::Bitboard, board::Board, definitions::MAX_MOVE_LIST_SIZE, moves::Move,
+    pieces::Piece, side::Side,
 };
 
+use crate::{evaluation::Evaluation, hce_values::ByteKnightValues, score::LargeScoreType};
+
 #[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
 pub enum MoveOrder {
     #[default]
     TtMove,
-    Capture(Piece, Piece),
+    /// Capture with the victim, attacker and capture history score.
+    Capture(Piece, Piece, LargeScoreType),
+    /// Killer move, with the index of the killer slot it came from.
+    Killer(usize),
+    Countermove,
     Quiet(LargeScoreType),
+    /// Capture that loses material according to SEE and doesn't give check, with the victim,
+    /// attacker and capture history score.
+    BadCapture(Piece, Piece, LargeScoreType),
 }
 
 impl PartialOrd for MoveOrder {
@@ -35,36 +43,71 @@ impl Ord for MoveOrder {
             (MoveOrder::TtMove, _) => Ordering::Less,
             (_, MoveOrder::TtMove) => Ordering::Greater,
 
-            // captures come next, according to MVV/LVA
+            // captures come next, according to MVV/LVA plus their capture history
             (
-                MoveOrder::Capture(left_victim, left_attacker),
-                MoveOrder::Capture(right_victim, right_attacker),
+                MoveOrder::Capture(left_victim, left_attacker, left_history),
+                MoveOrder::Capture(right_victim, right_attacker, right_history),
             ) => {
                 let left_value =
-                    Evaluation::<ByteKnightValues>::mvv_lva(*left_victim, *left_attacker);
+                    Evaluation::<ByteKnightValues>::mvv_lva(*left_victim, *left_attacker)
+                        + left_history;
                 let right_value =
-                    Evaluation::<ByteKnightValues>::mvv_lva(*right_victim, *right_attacker);
+                    Evaluation::<ByteKnightValues>::mvv_lva(*right_victim, *right_attacker)
+                        + right_history;
                 right_value.cmp(&left_value)
             }
-            (MoveOrder::Capture(_, _), _) => Ordering::Less,
-            (_, MoveOrder::Capture(_, _)) => Ordering::Greater,
+            (MoveOrder::Capture(..), _) => Ordering::Less,
+            (_, MoveOrder::Capture(..)) => Ordering::Greater,
+
+            // then killer moves, most recent first
+            (MoveOrder::Killer(left_slot), MoveOrder::Killer(right_slot)) => {
+                left_slot.cmp(right_slot)
+            }
+            (MoveOrder::Killer(_), _) => Ordering::Less,
+            (_, MoveOrder::Killer(_)) => Ordering::Greater,
+
+            // then the countermove to the previous move
+            (MoveOrder::Countermove, MoveOrder::Countermove) => Ordering::Equal,
+            (MoveOrder::Countermove, _) => Ordering::Less,
+            (_, MoveOrder::Countermove) => Ordering::Greater,
 
-            // quiet moves come last, according to their score
+            // then quiet moves, according to their score
             (MoveOrder::Quiet(left_score), MoveOrder::Quiet(right_score)) => {
                 right_score.cmp(left_score)
             }
+            (MoveOrder::Quiet(_), _) => Ordering::Less,
+            (_, MoveOrder::Quiet(_)) => Ordering::Greater,
+
+            // losing captures come last, using the same ordering as the other captures
+            (
+                MoveOrder::BadCapture(left_victim, left_attacker, left_history),
+                MoveOrder::BadCapture(right_victim, right_attacker, right_history),
+            ) => {
+                let left_value =
+                    Evaluation::<ByteKnightValues>::mvv_lva(*left_victim, *left_attacker)
+                        + left_history;
+                let right_value =
+                    Evaluation::<ByteKnightValues>::mvv_lva(*right_victim, *right_attacker)
+                        + right_history;
+                right_value.cmp(&left_value)
+            }
         }
     }
 }
 
 impl MoveOrder {
     /// Classify moves for move ordering  purposes.
+    /// Captures and quiet moves that are not killers or countermoves use `history_score` to break
+    /// ties. Captures that lose material according to SEE are ordered after the quiet moves,
+    /// unless they give check. Checks are forcing, so SEE can't tell if they really lose material.
     #[allow(clippy::expect_used)]
     pub fn classify(
-        stm: Side,
+        board: &Board,
         mv: &Move,
         tt_move: &Option<Move>,
-        history_table: &history_table::HistoryTable,
+        killers: &[Option<Move>],
+        countermove: &Option<Move>,
+        history_score: &impl Fn(&Move) -> LargeScoreType,
     ) -> Self {
         if tt_move.is_some_and(|tt| *mv == tt) {
             return Self::TtMove;
@@ -73,44 +116,78 @@ impl MoveOrder {
         if mv.is_capture() {
             let victim = mv.captured_piece().expect("Capture move without victim");
             let attacker = mv.piece();
-            return Self::Capture(victim, attacker);
+            let history = history_score(mv);
+            return if board.see(mv, 0) || gives_direct_check(board, mv) {
+                Self::Capture(victim, attacker, history)
+            } else {
+                Self::BadCapture(victim, attacker, history)
+            };
         }
 
-        let score = history_table.get(stm, mv.piece(), mv.to());
-        Self::Quiet(score)
+        if let Some(slot) = killers.iter().position(|killer| *killer == Some(*mv)) {
+            return Self::Killer(slot);
+        }
+
+        if countermove.is_some_and(|counter| *mv == counter) {
+            return Self::Countermove;
+        }
+
+        Self::Quiet(history_score(mv))
     }
 
     pub fn classify_all(
-        stm: Side,
+        board: &Board,
         moves: &[Move],
         tt_move: &Option<Move>,
-        history_table: &history_table::HistoryTable,
+        killers: &[Option<Move>],
+        countermove: &Option<Move>,
+        history_score: &impl Fn(&Move) -> LargeScoreType,
         move_order: &mut ArrayVec<MoveOrder, MAX_MOVE_LIST_SIZE>,
     ) -> Result<()> {
         move_order.clear();
 
         for mv in moves.iter() {
-            move_order.try_push(Self::classify(stm, mv, tt_move, history_table))?;
+            move_order.try_push(Self::classify(
+                board,
+                mv,
+                tt_move,
+                killers,
+                countermove,
+                history_score,
+            ))?;
         }
 
         Ok(())
     }
 }
 
+/// Returns true if the piece moved by `mv` attacks the enemy king from its destination square.
+/// Discovered checks are not detected.
+pub(crate) fn gives_direct_check(board: &Board, mv: &Move) -> bool {
+    let stm = board.side_to_move();
+    let piece = mv.promotion_piece().unwrap_or(mv.piece());
+    let mut occupied = board.all_pieces();
+    occupied.clear_square(mv.from());
+    occupied.set_square(mv.to());
+
+    let king = Bitboard::from_square(board.king_square(Side::opposite(stm)));
+    attacks::for_piece(piece, mv.to(), occupied, stm) & king != 0
+}
+
 #[cfg(test)]
 mod tests {
     use chess::{board::Board, move_generation::MoveGenerator, move_list::MoveList, moves::Move};
     use itertools::Itertools;
 
     use crate::{
-        move_order::MoveOrder,
+        move_order::{MoveOrder, gives_direct_check},
         score::Score,
         ttable::{EntryFlag, TranspositionTable, TranspositionTableEntry},
     };
 
     #[test]
     fn verify_move_ordering() {
-        let mut tt = TranspositionTable::from_capacity(10);
+        let tt = TranspositionTable::from_capacity(10);
         let mut history_table = crate::history_table::HistoryTable::new();
 
         let move_gen = MoveGenerator::new();
@@ -138,12 +215,14 @@ mod tests {
             300 * depth - 250,
         );
         let tt_entry = tt.get_entry(board.zobrist_hash()).unwrap();
-        let tt_move = tt_entry.board_move;
+        let tt_move = tt_entry.board_move.resolve(move_list.iter()).unwrap();
         // sort the moves
         let moves = move_list
             .iter()
             .sorted_by_key(|mv| {
-                MoveOrder::classify(board.side_to_move(), mv, &Some(tt_move), &history_table)
+                MoveOrder::classify(&board, mv, &Some(tt_move), &[], &None, &|mv: &Move| {
+                    history_table.get(board.side_to_move(), mv.piece(), mv.to())
+                })
             })
             .collect::<Vec<&Move>>();
 
@@ -152,6 +231,121 @@ mod tests {
         // check the order of the moves
     }
 
+    #[test]
+    fn killers_and_countermoves_are_ordered_before_quiets() {
+        let move_gen = MoveGenerator::new();
+        let mut move_list = MoveList::new();
+        let board = Board::from_fen("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        move_gen.generate_legal_moves(&board, &mut move_list);
+
+        let find = |name: &str| {
+            *move_list
+                .iter()
+                .find(|mv| mv.to_long_algebraic() == name)
+                .unwrap()
+        };
+
+        let mut history_table = crate::history_table::HistoryTable::new();
+        let pawn_push = find("e4e5");
+        history_table.update(
+            board.side_to_move(),
+            pawn_push.piece(),
+            pawn_push.to(),
+            1000,
+        );
+
+        let killers = [Some(find("e1f1")), Some(find("e1d2"))];
+        let countermove = Some(find("e1e2"));
+
+        let moves = move_list
+            .iter()
+            .sorted_by_key(|mv| {
+                MoveOrder::classify(&board, mv, &None, &killers, &countermove, &|mv: &Move| {
+                    history_table.get(board.side_to_move(), mv.piece(), mv.to())
+                })
+            })
+            .map(|mv| mv.to_long_algebraic())
+            .collect::<Vec<String>>();
+
+        assert_eq!(moves[..5], ["e4d5", "e1f1", "e1d2", "e1e2", "e4e5"]);
+    }
+
+    #[test]
+    fn capture_history_breaks_mvv_lva_ties() {
+        let move_gen = MoveGenerator::new();
+        let mut move_list = MoveList::new();
+        // the knight can capture either pawn, MVV/LVA can't tell them apart
+        let board = Board::from_fen("4k3/8/8/2p1p3/8/3N4/8/4K3 w - - 0 1").unwrap();
+        move_gen.generate_legal_moves(&board, &mut move_list);
+
+        for (preferred, other) in [("d3e5", "d3c5"), ("d3c5", "d3e5")] {
+            let moves = move_list
+                .iter()
+                .sorted_by_key(|mv| {
+                    MoveOrder::classify(&board, mv, &None, &[], &None, &|mv: &Move| {
+                        if mv.to_long_algebraic() == preferred {
+                            500
+                        } else {
+                            0
+                        }
+                    })
+                })
+                .map(|mv| mv.to_long_algebraic())
+                .collect::<Vec<String>>();
+
+            assert_eq!(moves[..2], [preferred, other]);
+        }
+    }
+
+    #[test]
+    fn losing_captures_are_ordered_after_quiets() {
+        let move_gen = MoveGenerator::new();
+        let mut move_list = MoveList::new();
+        // the pawn wins the d5 pawn, the queen loses itself to exd5
+        let board = Board::from_fen("4k3/8/4p3/3p4/2P5/8/8/3QK3 w - - 0 1").unwrap();
+        move_gen.generate_legal_moves(&board, &mut move_list);
+
+        let moves = move_list
+            .iter()
+            .sorted_by_key(|mv| MoveOrder::classify(&board, mv, &None, &[], &None, &|_| 0))
+            .map(|mv| mv.to_long_algebraic())
+            .collect::<Vec<String>>();
+
+        assert_eq!(moves.first().map(String::as_str), Some("c4d5"));
+        assert_eq!(moves.last().map(String::as_str), Some("d1d5"));
+    }
+
+    #[test]
+    fn losing_captures_that_give_check_stay_with_the_captures() {
+        let move_gen = MoveGenerator::new();
+        let mut move_list = MoveList::new();
+        // the queen still loses itself to exd5, but it gives check
+        let board = Board::from_fen("3k4/8/4p3/3p4/2P5/8/8/3QK3 w - - 0 1").unwrap();
+        move_gen.generate_legal_moves(&board, &mut move_list);
+
+        let moves = move_list
+            .iter()
+            .sorted_by_key(|mv| MoveOrder::classify(&board, mv, &None, &[], &None, &|_| 0))
+            .map(|mv| mv.to_long_algebraic())
+            .collect::<Vec<String>>();
+
+        assert_eq!(moves[..2], ["c4d5", "d1d5"]);
+    }
+
+    #[test]
+    fn direct_checks() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K1N1 w - - 0 1").unwrap();
+        let mut move_list = MoveList::new();
+        MoveGenerator::new().generate_legal_moves(&board, &mut move_list);
+
+        let checks = move_list
+            .iter()
+            .filter(|mv| gives_direct_check(&board, mv))
+            .map(|mv| mv.to_long_algebraic())
+            .collect::<Vec<String>>();
+        assert_eq!(checks, ["a1a8"]);
+    }
+
     // TODO(PT): Re-enable benchmark when bench is stablized (if ever)
     // #[bench]
     // fn bench_move_ordering(b: &mut Bencher) {
@@ -0,0 +1,155 @@
+// Part of the byte-knight project.
+// Author: Paul Tsouchlos (ptsouchlos) (developer.paul.123@gmail.com)
+// GNU General Public License v3.0 or later
+// https://www.gnu.org/licenses/gpl-3.0-standalone.html
+
+use chess::{move_list::MoveList, moves::Move};
+
+use crate::{principle_variation::PrincipleVariation, score::Score};
+
+/// A move that can be searched at the root, with what the search has learned about it.
+#[derive(Clone, Debug)]
+pub(crate) struct RootMove {
+    pub(crate) mv: Move,
+    /// Score of the move in the current iteration. Moves that haven't raised alpha in this
+    /// iteration only have an upper bound, and are scored as `-Score::INF`.
+    pub(crate) score: Score,
+    /// Score of the move at the end of the previous iteration.
+    pub(crate) previous_score: Score,
+    /// Nodes searched below this move since the search started.
+    pub(crate) nodes: u64,
+    /// Principle variation starting with this move, from the last time it raised alpha.
+    pub(crate) pv: PrincipleVariation,
+}
+
+impl RootMove {
+    fn new(mv: Move) -> Self {
+        let mut pv = PrincipleVariation::new();
+        pv.push(mv);
+        Self {
+            mv,
+            score: -Score::INF,
+            previous_score: -Score::INF,
+            nodes: 0,
+            pv,
+        }
+    }
+}
+
+/// The moves searched at the root of the search. Unlike the transposition table, this keeps
+/// track of every root move for the whole search, so the best move is always known.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RootMoves {
+    moves: Vec<RootMove>,
+}
+
+impl RootMoves {
+    /// Creates the root moves from the moves that can be searched at the root.
+    pub(crate) fn new(move_list: &MoveList) -> Self {
+        Self {
+            moves: move_list.iter().copied().map(RootMove::new).collect(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub(crate) fn contains(&self, mv: &Move) -> bool {
+        self.find(mv).is_some()
+    }
+
+    pub(crate) fn find(&self, mv: &Move) -> Option<&RootMove> {
+        self.moves.iter().find(|root_move| root_move.mv == *mv)
+    }
+
+    /// Returns the best root move. This is only up to date after [`RootMoves::sort`].
+    pub(crate) fn best(&self) -> Option<&RootMove> {
+        self.moves.first()
+    }
+
+    /// Starts a new iteration of iterative deepening, the scores of the last one become the
+    /// previous scores.
+    pub(crate) fn new_iteration(&mut self) {
+        for root_move in &mut self.moves {
+            root_move.previous_score = root_move.score;
+            root_move.score = -Score::INF;
+        }
+    }
+
+    /// Records the search of the root move `mv`, which took `nodes` nodes. `result` has the score
+    /// and the principle variation after `mv` if the move raised alpha.
+    pub(crate) fn update(
+        &mut self,
+        mv: &Move,
+        nodes: u64,
+        result: Option<(Score, &PrincipleVariation)>,
+    ) {
+        let Some(root_move) = self.moves.iter_mut().find(|root_move| root_move.mv == *mv) else {
+            return;
+        };
+
+        root_move.nodes += nodes;
+        match result {
+            Some((score, pv)) => {
+                root_move.score = score;
+                root_move.pv.extend(*mv, pv);
+            }
+            None => root_move.score = -Score::INF,
+        }
+    }
+
+    /// Sorts the root moves from best to worst. Moves with the same score keep the order of the
+    /// previous iteration, so moves that only have an upper bound stay where they were.
+    pub(crate) fn sort(&mut self) {
+        self.moves.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| b.previous_score.cmp(&a.previous_score))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::{board::Board, move_generation::MoveGenerator, move_list::MoveList};
+
+    use super::RootMoves;
+    use crate::{principle_variation::PrincipleVariation, score::Score};
+
+    #[test]
+    fn root_moves_are_sorted_by_score() {
+        let board = Board::default_board();
+        let mut move_list = MoveList::new();
+        MoveGenerator::new().generate_legal_moves(&board, &mut move_list);
+        let mut root_moves = RootMoves::new(&move_list);
+        assert_eq!(root_moves.len(), 20);
+
+        let first = *move_list.at(0).unwrap();
+        let second = *move_list.at(1).unwrap();
+        let third = *move_list.at(2).unwrap();
+        let pv = PrincipleVariation::new();
+
+        root_moves.new_iteration();
+        root_moves.update(&first, 100, Some((Score::new(10), &pv)));
+        root_moves.update(&second, 50, Some((Score::new(30), &pv)));
+        root_moves.update(&third, 10, None);
+        root_moves.sort();
+        let best = root_moves.best().unwrap();
+        assert_eq!(best.mv, second);
+        assert_eq!(best.pv.iter().next(), Some(&second));
+
+        // in the next iteration the old best move fails low, but the others keep their order
+        root_moves.new_iteration();
+        root_moves.update(&second, 200, None);
+        root_moves.update(&first, 100, None);
+        root_moves.sort();
+        let best = root_moves.best().unwrap();
+        assert_eq!(best.mv, second);
+        assert_eq!(best.nodes, 250);
+        assert_eq!(
+            root_moves.find(&first).unwrap().previous_score,
+            Score::new(10)
+        );
+    }
+}
@@ -75,20 +75,61 @@ impl Score {
     pub fn mated(&self) -> bool {
         self.0 <= -Score::MINIMUM_MATE.0
     }
+
+    /// Returns the number of moves until mate if this is a mate score. The count is negative if the
+    /// side to move is the one being mated.
+    pub fn moves_to_mate(&self) -> Option<ScoreType> {
+        if !self.is_mate() {
+            return None;
+        }
+
+        let plies = Score::MATE.0 - self.0.abs();
+        if self.0 > 0 {
+            Some((plies + 1) / 2)
+        } else {
+            Some(-plies / 2)
+        }
+    }
+
+    /// Converts a score relative to the root into one relative to the node at `ply`, so that it
+    /// can be stored in the transposition table. Only mate scores are affected.
+    pub fn to_tt(&self, ply: ScoreType) -> Score {
+        if self.0 >= Score::MINIMUM_MATE.0 {
+            *self + ply
+        } else if self.mated() {
+            *self - ply
+        } else {
+            *self
+        }
+    }
+
+    /// Converts a score read from the transposition table at `ply` back into one relative to the
+    /// root. This is the inverse of [`Score::to_tt`].
+    pub fn from_tt(&self, ply: ScoreType) -> Score {
+        if self.0 >= Score::MINIMUM_MATE.0 {
+            *self - ply
+        } else if self.mated() {
+            *self + ply
+        } else {
+            *self
+        }
+    }
 }
 
 impl From<Score> for UciScore {
     fn from(value: Score) -> Self {
-        UciScore::cp(value.0.into())
+        match value.moves_to_mate() {
+            Some(moves) => UciScore::mate(moves.into()),
+            None => UciScore::cp(value.0.into()),
+        }
     }
 }
 
 impl Display for Score {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        if self.0.abs() >= Score::MATE.0.abs() {
-            write!(f, "mate {}", (self.0 - Score::MATE.0) / 2)
-        } else {
-            write!(f, "cp {}", self.0)
+        match self.moves_to_mate() {
+            Some(moves) => write!(f, "mate {moves}"),
+            None => write!(f, "cp {}", self.0),
         }
     }
 }
@@ -223,4 +264,44 @@ mod tests {
         right += Score::INF;
         assert_eq!(right, Score::INF);
     }
+
+    #[test]
+    fn mate_scores() {
+        // mate in 1 and 2 for the side to move
+        assert_eq!((Score::MATE - 1).moves_to_mate(), Some(1));
+        assert_eq!((Score::MATE - 3).moves_to_mate(), Some(2));
+        // side to move is mated now, or in 1 and 2 moves
+        assert_eq!((-Score::MATE).moves_to_mate(), Some(0));
+        assert_eq!((-Score::MATE + 2).moves_to_mate(), Some(-1));
+        assert_eq!((-Score::MATE + 4).moves_to_mate(), Some(-2));
+        assert_eq!(Score::new(300).moves_to_mate(), None);
+
+        assert_eq!(UciScore::from(Score::MATE - 3), UciScore::mate(2));
+        assert_eq!(UciScore::from(-Score::MATE + 4), UciScore::mate(-2));
+        assert_eq!(UciScore::from(Score::new(-42)), UciScore::cp(-42));
+
+        // display agrees with the UCI output
+        for score in [Score::MATE - 5, -Score::MATE + 2, Score::new(17)] {
+            assert_eq!(score.to_string(), UciScore::from(score).to_string());
+        }
+    }
+
+    #[test]
+    fn tt_mate_adjustment() {
+        // mate in 3 plies from the root, seen from a node 2 plies deep
+        let mate = Score::MATE - 3;
+        assert_eq!(mate.to_tt(2), Score::MATE - 1);
+        assert_eq!(mate.to_tt(2).from_tt(2), mate);
+        // the same entry probed from a different ply is a mate at a different distance
+        assert_eq!(mate.to_tt(2).from_tt(4), Score::MATE - 5);
+
+        let mated = -Score::MATE + 4;
+        assert_eq!(mated.to_tt(3), -Score::MATE + 1);
+        assert_eq!(mated.to_tt(3).from_tt(3), mated);
+
+        // normal scores are not changed
+        let score = Score::new(150);
+        assert_eq!(score.to_tt(10), score);
+        assert_eq!(score.from_tt(10), score);
+    }
 }
@@ -17,28 +17,40 @@ use anyhow::{Result, bail};
 use arrayvec::ArrayVec;
 use chess::{
     board::Board, definitions::MAX_MOVE_LIST_SIZE, move_generation::MoveGenerator,
-    move_list::MoveList, moves::Move, pieces::Piece,
+    move_list::MoveList, moves::Move, pieces::Piece, side::Side,
 };
-use uci_parser::{UciInfo, UciResponse, UciScore, UciSearchOptions};
+use uci_parser::{UciBound, UciInfo, UciResponse, UciScore, UciSearchOptions};
 
 use crate::{
     aspiration_window::AspirationWindow,
+    capture_history::CaptureHistory,
+    continuation_history::ContinuationHistory,
+    correction_history::CorrectionHistory,
+    countermove_table::CountermoveTable,
     defs::MAX_DEPTH,
     evaluation::ByteKnightEvaluation,
     history_table::{self, HistoryTable},
     inplace_incremental_sort::InplaceIncrementalSort,
     lmr,
     log_level::LogLevel,
-    move_order::MoveOrder,
+    move_order::{MoveOrder, gives_direct_check},
     node_types::{NodeType, NonPvNode, PvNode, RootNode},
     principle_variation::PrincipleVariation,
+    root_moves::RootMoves,
     score::{LargeScoreType, Score, ScoreType},
+    search_stack::SearchStack,
     table::Table,
+    time_manager::{self, TimeManager},
     traits::Eval,
     ttable::{self, TranspositionTableEntry},
     tuneable::{
-        IIR_DEPTH_REDUCTION, IIR_MIN_DEPTH, LMP_MIN_THRESHOLD_DEPTH, MAX_RFP_DEPTH,
-        NMP_DEPTH_REDUCTION, NMP_MIN_DEPTH, RFP_MARGIN,
+        FP_BASE_MARGIN, FP_MARGIN, HISTORY_PRUNING_MARGIN, IIR_DEPTH_REDUCTION, IIR_MIN_DEPTH,
+        LMP_MIN_THRESHOLD_DEPTH, LMR_CAPTURE_HISTORY_DIVISOR, LMR_HISTORY_DIVISOR, LMR_MIN_DEPTH,
+        MAX_FP_DEPTH, MAX_HISTORY_PRUNING_DEPTH, MAX_RAZORING_DEPTH, MAX_RFP_DEPTH,
+        MAX_SEE_PRUNING_DEPTH, NMP_DEPTH_DIVISOR, NMP_DEPTH_REDUCTION, NMP_EVAL_REDUCTION_DIVISOR,
+        NMP_MAX_EVAL_REDUCTION, NMP_MIN_DEPTH, NMP_VERIFICATION_DEPTH, PROBCUT_DEPTH_REDUCTION,
+        PROBCUT_MARGIN, PROBCUT_MIN_DEPTH, RAZORING_MARGIN, RFP_MARGIN, SE_MARGIN, SE_MIN_DEPTH,
+        SE_TT_DEPTH_MARGIN, SEE_CAPTURE_MARGIN, SEE_QUIET_MARGIN,
     },
 };
 use ttable::TranspositionTable;
@@ -80,6 +92,21 @@ impl Display for SearchResult {
     }
 }
 
+/// A line found by the search, reported in its own `info` line in MultiPV mode.
+#[derive(Clone, Debug, Default)]
+struct PvLine {
+    score: Score,
+    /// Set when the search was stopped before the score of this line was exact.
+    bound: Option<UciBound>,
+    pv: PrincipleVariation,
+}
+
+/// Largest number of lines that can be requested with the `MultiPV` option.
+pub const MAX_MULTI_PV: usize = MAX_MOVE_LIST_SIZE;
+
+/// Largest contempt that can be set with the `Contempt` option, in centipawns.
+pub const MAX_CONTEMPT: ScoreType = 100;
+
 /// Input parameters for the search.
 #[derive(Clone, Debug)]
 pub struct SearchParameters {
@@ -88,6 +115,22 @@ pub struct SearchParameters {
     pub soft_timeout: Duration,
     pub hard_timeout: Duration,
     pub max_nodes: u64,
+    /// Index of the thread running the search. The main thread is always `0`.
+    pub thread_id: usize,
+    /// How much worse than equal a draw is for the side that started the search, in centipawns.
+    /// Negative values make the engine prefer draws.
+    pub contempt: ScoreType,
+    /// Number of lines to search and report, see the `MultiPV` option.
+    pub multi_pv: usize,
+    /// Root moves to search, given with `go searchmoves`. All legal moves are searched if empty.
+    pub search_moves: Vec<Move>,
+    /// Set for `go infinite` searches, which only end on `stop`.
+    pub infinite: bool,
+    /// Stop as soon as a mate in at most this many moves is found, set with `go mate`.
+    pub mate: Option<u32>,
+    /// Set for `go ponder` searches. Time limits are ignored while the flag is `true`, and the
+    /// engine clears it on `ponderhit`.
+    pub ponder: Option<Arc<AtomicBool>>,
 }
 
 impl Default for SearchParameters {
@@ -98,13 +141,21 @@ impl Default for SearchParameters {
             soft_timeout: Duration::MAX,
             hard_timeout: Duration::MAX,
             max_nodes: u64::MAX,
+            thread_id: 0,
+            contempt: 0,
+            multi_pv: 1,
+            search_moves: Vec::new(),
+            infinite: false,
+            mate: None,
+            ponder: None,
         }
     }
 }
 
 impl SearchParameters {
     /// Creates a new set of search parameters from the UCI options and the current board.
-    pub fn new(uci_options: &UciSearchOptions, board: &Board) -> Self {
+    /// `move_overhead` is kept back from the clock for every move, see the `Move Overhead` option.
+    pub fn new(uci_options: &UciSearchOptions, board: &Board, move_overhead: Duration) -> Self {
         let mut params = Self::default();
         if let Some(depth) = uci_options.depth {
             params.max_depth = depth as u8;
@@ -114,48 +165,114 @@ impl SearchParameters {
             params.max_nodes = nodes as u64;
         }
 
-        if let Some(time) = uci_options.movetime {
-            params.soft_timeout = time;
-            params.hard_timeout = time;
-        } else {
-            let (time, increment) = if board.side_to_move().is_white() {
-                (uci_options.wtime, uci_options.winc)
-            } else {
-                (uci_options.btime, uci_options.binc)
-            };
+        if uci_options.ponder {
+            params.ponder = Some(Arc::new(AtomicBool::new(true)));
+        }
 
-            // do we have valid time
-            if let Some(time) = time {
-                // TODO: How can we tune these params?
-                let inc = increment.unwrap_or(Duration::ZERO) / 2;
-                params.soft_timeout = time / 20 + inc;
-                params.hard_timeout = time / 5 + inc;
-            }
+        params.infinite = uci_options.infinite;
+        params.mate = uci_options.mate;
+
+        if !uci_options.searchmoves.is_empty() {
+            let mut move_list = MoveList::new();
+            MoveGenerator::new().generate_legal_moves(board, &mut move_list);
+            params.search_moves = move_list
+                .iter()
+                .filter(|mv| {
+                    uci_options
+                        .searchmoves
+                        .iter()
+                        .any(|uci_move| uci_move.to_string() == mv.to_long_algebraic())
+                })
+                .copied()
+                .collect();
+        }
+
+        if let Some((soft_timeout, hard_timeout)) =
+            time_manager::time_limits(uci_options, board.side_to_move(), move_overhead)
+        {
+            params.soft_timeout = soft_timeout;
+            params.hard_timeout = hard_timeout;
         }
 
         params
     }
+
+    /// Creates the parameters for a Lazy SMP helper thread. Helpers have no limits of their own
+    /// and keep searching until the main thread tells them to stop.
+    pub(crate) fn for_helper(&self, thread_id: usize) -> Self {
+        SearchParameters {
+            start_time: self.start_time,
+            thread_id,
+            contempt: self.contempt,
+            search_moves: self.search_moves.clone(),
+            ..Default::default()
+        }
+    }
 }
 
 impl Display for SearchParameters {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "max depth {} start_time {:?} soft_timeout {:?} hard_timeout {:?}",
-            self.max_depth, self.start_time, self.soft_timeout, self.hard_timeout
+            "max depth {} start_time {:?} soft_timeout {:?} hard_timeout {:?} ponder {}",
+            self.max_depth,
+            self.start_time,
+            self.soft_timeout,
+            self.hard_timeout,
+            self.ponder.is_some()
         )
     }
 }
 
+/// Lazy SMP helper threads skip some iterations of iterative deepening so that they are not all
+/// searching the same depth as the main thread. Each helper uses a different size/phase pair.
+/// See <https://www.chessprogramming.org/Lazy_SMP>
+const SKIP_SIZE: [u8; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+const SKIP_PHASE: [u8; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+
+/// Returns true if the thread with the given id should skip the iteration at the given depth.
+/// The main thread (id `0`) never skips a depth.
+fn should_skip_depth(thread_id: usize, depth: u8) -> bool {
+    if thread_id == 0 {
+        return false;
+    }
+
+    let index = (thread_id - 1) % SKIP_SIZE.len();
+    !(depth.saturating_add(SKIP_PHASE[index]) / SKIP_SIZE[index]).is_multiple_of(2)
+}
+
+/// Distance, in plies, to the earlier move used by each continuation history table.
+const CONTINUATION_HISTORY_PLIES: [ScoreType; 2] = [1, 2];
+
+/// Deepest ply the search will reach, extensions included.
+const MAX_PLY: ScoreType = MAX_DEPTH as ScoreType - 1;
+
 pub struct Search<'search_lifetime, Log> {
-    transposition_table: &'search_lifetime mut TranspositionTable,
+    transposition_table: &'search_lifetime TranspositionTable,
     history_table: &'search_lifetime mut HistoryTable,
+    countermove_table: CountermoveTable,
+    continuation_history: [ContinuationHistory; CONTINUATION_HISTORY_PLIES.len()],
+    capture_history: CaptureHistory,
+    correction_history: &'search_lifetime mut CorrectionHistory,
+    search_stack: SearchStack,
+    /// Null moves are disabled for `nmp_side` below this ply while verifying a null move cutoff.
+    nmp_min_ply: ScoreType,
+    nmp_side: Side,
+    /// Side to move at the root, draws are scored from its point of view.
+    root_side: Side,
+    /// Root moves of the lines already found in this iteration of a MultiPV search.
+    excluded_root_moves: Vec<Move>,
+    /// Root move searched first while `excluded_root_moves` isn't empty.
+    root_move_hint: Option<Move>,
+    root_moves: RootMoves,
+    time_manager: TimeManager,
     move_gen: MoveGenerator,
     nodes: u64,
     parameters: SearchParameters,
     eval: ByteKnightEvaluation,
     stop_flag: Option<Arc<AtomicBool>>,
     lmr_table: Table<f64, 32_000>,
+    capture_lmr_table: Table<f64, 32_000>,
     /// Marker for the level of logging to print.
     log: PhantomData<Log>,
 }
@@ -163,22 +280,38 @@ pub struct Search<'search_lifetime, Log> {
 impl<'a, Log: LogLevel> Search<'a, Log> {
     pub fn new(
         parameters: &SearchParameters,
-        ttable: &'a mut TranspositionTable,
+        ttable: &'a TranspositionTable,
         history_table: &'a mut HistoryTable,
+        correction_history: &'a mut CorrectionHistory,
     ) -> Self {
-        // Initialize our LMR table as a 2D array of our LMR formula for depth and moves played
+        // Initialize our LMR tables as 2D arrays of our LMR formulas for depth and moves played
         let mut table = Table::<f64, 32_000>::new(MAX_DEPTH as usize, MAX_MOVE_LIST_SIZE);
         table.fill(lmr::formula);
+        let mut capture_table = Table::<f64, 32_000>::new(MAX_DEPTH as usize, MAX_MOVE_LIST_SIZE);
+        capture_table.fill(lmr::capture_formula);
 
         Self {
             transposition_table: ttable,
             history_table,
+            countermove_table: CountermoveTable::default(),
+            continuation_history: Default::default(),
+            capture_history: CaptureHistory::default(),
+            correction_history,
+            search_stack: SearchStack::default(),
+            nmp_min_ply: 0,
+            nmp_side: Side::White,
+            root_side: Side::White,
+            excluded_root_moves: Vec::new(),
+            root_move_hint: None,
+            root_moves: RootMoves::default(),
+            time_manager: TimeManager::default(),
             move_gen: MoveGenerator::new(),
             nodes: 0,
             parameters: parameters.clone(),
             eval: ByteKnightEvaluation::default(),
             stop_flag: None,
             lmr_table: table,
+            capture_lmr_table: capture_table,
             log: PhantomData,
         }
     }
@@ -186,6 +319,9 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
     /// Search for the best move in the given board state. This will output
     /// UCI info lines as it searches.
     ///
+    /// A ponder or infinite search parks the calling thread once it is done, until the stop flag
+    /// is set or pondering ends. Whoever sets either flag has to unpark the thread.
+    ///
     /// # Arguments
     ///
     /// - `board` - The current board state.
@@ -200,6 +336,13 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
         stop_flag: Option<Arc<AtomicBool>>,
     ) -> SearchResult {
         self.stop_flag = stop_flag;
+        // killers and countermoves from a previous search are for a different position
+        self.countermove_table.clear();
+        self.search_stack.clear();
+        self.nmp_min_ply = 0;
+        self.root_side = board.side_to_move();
+        self.time_manager =
+            TimeManager::new(self.parameters.soft_timeout, self.parameters.hard_timeout);
 
         if Log::DEBUG {
             self.send_message(format!("starting search for FEN {}", board.to_fen()));
@@ -207,7 +350,8 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
         }
 
         let mut ml = MoveList::new();
-        self.move_gen.generate_legal_moves(board, &mut ml);
+        self.generate_root_moves(board, &mut ml);
+        self.root_moves = RootMoves::new(&ml);
         let mut result = match ml.len() {
             0 => {
                 // Draw or something else?
@@ -244,45 +388,118 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
 
         // Try to ensure we have a move
         if result.best_move.is_none()
-            && let Some(mv) = ml.as_slice().first().copied()
+            && let Some(root_move) = self.root_moves.best()
         {
-            result.best_move = Some(mv);
+            result.best_move = Some(root_move.mv);
             result.score = self.eval.eval(board);
         }
 
+        // a ponder or infinite search must not report its move before `ponderhit` or `stop`
+        while (self.is_pondering() || self.parameters.infinite) && !self.stop_flag_is_set() {
+            std::thread::park();
+        }
+
         // search ended, reset our node count
         self.nodes = 0;
         result
     }
 
-    fn should_stop_searching(&self) -> bool {
-        self.parameters.start_time.elapsed() >= self.parameters.hard_timeout // hard timeout
+    /// Returns true if `mv` can be searched at the root, see [`SearchParameters::search_moves`].
+    fn is_searched_root_move(&self, mv: &Move) -> bool {
+        self.parameters.search_moves.is_empty() || self.parameters.search_moves.contains(mv)
+    }
+
+    /// Generates the legal moves that can be searched at the root.
+    fn generate_root_moves(&self, board: &Board, move_list: &mut MoveList) {
+        self.move_gen.generate_legal_moves(board, move_list);
+        move_list.retain(|mv| self.is_searched_root_move(mv));
+    }
+
+    /// Returns true if `score` proves a mate within the number of moves given with `go mate`.
+    fn is_requested_mate(&self, score: Score) -> bool {
+        self.parameters.mate.is_some_and(|mate| {
+            score
+                .moves_to_mate()
+                .is_some_and(|moves| moves > 0 && moves as u32 <= mate)
+        })
+    }
+
+    fn stop_flag_is_set(&self) -> bool {
+        self.stop_flag
+            .as_ref()
+            .is_some_and(|f| f.load(Ordering::Relaxed))
+    }
+
+    /// Returns `true` while pondering. On `ponderhit` the search becomes a normal search, and its
+    /// time limits apply from that moment on.
+    fn is_pondering(&mut self) -> bool {
+        let Some(ponder) = &self.parameters.ponder else {
+            return false;
+        };
+        if ponder.load(Ordering::Relaxed) {
+            return true;
+        }
+
+        // our clock only started running when the opponent played the expected move
+        self.parameters.ponder = None;
+        self.parameters.start_time = Instant::now();
+        false
+    }
+
+    /// Returns the score of a drawn position for the side to move in `board`. With contempt, the
+    /// side that started the search scores draws as slightly losing and its opponent as slightly
+    /// winning. The score is also nudged by one centipawn either way depending on the node count,
+    /// so repetition lines don't all look the same, while keeping node limited searches
+    /// deterministic.
+    fn draw_score(&self, board: &Board) -> Score {
+        let contempt = if board.side_to_move() == self.root_side {
+            -self.parameters.contempt
+        } else {
+            self.parameters.contempt
+        };
+        let jitter = 1 - (self.nodes & 2) as ScoreType;
+        Score::new(contempt + jitter)
+    }
+
+    fn should_stop_searching(&mut self) -> bool {
+        (!self.is_pondering() && self.parameters.start_time.elapsed() >= self.parameters.hard_timeout) // hard timeout
             || self.nodes >= self.parameters.max_nodes // node limit reached
-            || self.stop_flag.as_ref().is_some_and(|f| f.load(Ordering::Relaxed))
-        // stop flag set
+            || self.stop_flag_is_set() // stop flag set
     }
 
-    fn send_info(
-        &self,
-        depth: u8,
-        nodes: u64,
-        score: Score,
-        nps: f32,
-        time: u64,
-        pv: &PrincipleVariation,
-    ) {
+    fn send_info(&self, depth: u8, multipv: Option<usize>, line: &PvLine) {
+        let elapsed = self.parameters.start_time.elapsed();
+        let mut score = UciScore::from(line.score);
+        score.bound = line.bound;
+
         // create UciInfo and print it
-        let info = UciInfo::new()
-            .depth(depth)
-            .nodes(nodes)
+        let mut info = UciInfo::new().depth(depth);
+        if let Some(multipv) = multipv {
+            info = info.multipv(multipv);
+        }
+        let info = info
+            .nodes(self.nodes)
             .score(score)
-            .nps(nps.trunc())
-            .time(time)
-            .pv(pv.iter().map(|m| m.to_long_algebraic()));
+            .nps((self.nodes as f32 / elapsed.as_secs_f32()).trunc())
+            .time(elapsed.as_millis() as u64)
+            .hashfull(self.transposition_table.hashfull())
+            .pv(line.pv.iter().map(|m| m.to_long_algebraic()));
         let message = UciResponse::info(info);
         println!("{message}");
     }
 
+    /// Sends an info line for every line of a MultiPV search, or a single line without the
+    /// `multipv` field for a normal search.
+    fn send_lines(&self, depth: u8, lines: &[PvLine]) {
+        if let [line] = lines {
+            self.send_info(depth, None, line);
+        } else {
+            for (i, line) in lines.iter().enumerate() {
+                self.send_info(depth, Some(i + 1), line);
+            }
+        }
+    }
+
     fn send_message(&self, message: String) {
         let info = UciInfo::default().string(message);
         let message = UciResponse::info(info);
@@ -311,102 +528,163 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
     /// we stop searching and return the best move found so far.
     fn iterative_deepening(&mut self, board: &mut Board) -> SearchResult {
         // initialize the best result
-        let mut best_result = SearchResult::default();
-        let mut move_list = MoveList::new();
+        let mut best_result = SearchResult {
+            best_move: self.root_moves.best().map(|root_move| root_move.mv),
+            ..Default::default()
+        };
 
-        self.move_gen.generate_legal_moves(board, &mut move_list);
-        if !move_list.is_empty() {
-            best_result.best_move = Some(*move_list.at(0).unwrap())
-        }
+        // in MultiPV mode we search one line per root move, up to the number of legal moves
+        let line_count = self
+            .parameters
+            .multi_pv
+            .clamp(1, self.root_moves.len().max(1));
+        let mut lines = vec![PvLine::default(); line_count];
 
-        'deepening: while self.parameters.start_time.elapsed() <= self.parameters.soft_timeout
+        'deepening: while (self.is_pondering()
+            || self.parameters.start_time.elapsed() <= self.time_manager.soft_limit())
             && best_result.depth <= self.parameters.max_depth
-            && !self
-                .stop_flag
-                .as_ref()
-                .is_some_and(|f| f.load(Ordering::Relaxed))
+            && !self.stop_flag_is_set()
         {
-            // create an aspiration window around the best result so far
-            let mut aspiration_window =
-                AspirationWindow::around(best_result.score, best_result.depth as ScoreType);
-            let mut pv = PrincipleVariation::new();
-
-            let mut score: Score;
-            'aspiration_window: loop {
-                // search the tree, starting at the current depth (starts at 1)
-                score = self.negamax::<RootNode>(
-                    board,
+            // helper threads stagger the depths they search
+            if should_skip_depth(self.parameters.thread_id, best_result.depth) {
+                best_result.depth += 1;
+                continue 'deepening;
+            }
+
+            self.root_moves.new_iteration();
+            for line_index in 0..line_count {
+                // the moves of the lines above this one are excluded, so that this line finds the
+                // next best move
+                self.excluded_root_moves = lines[..line_index]
+                    .iter()
+                    .filter_map(|line| line.pv.iter().next().copied())
+                    .collect();
+                // search the move this line had in the last iteration first, so the order of the
+                // root moves stays the same from one iteration to the next
+                self.root_move_hint = lines[line_index]
+                    .pv
+                    .iter()
+                    .next()
+                    .copied()
+                    .filter(|mv| !self.excluded_root_moves.contains(mv));
+
+                // create an aspiration window around the score of this line so far
+                let mut aspiration_window = AspirationWindow::around(
+                    lines[line_index].score,
                     best_result.depth as ScoreType,
-                    0,
-                    aspiration_window.alpha(),
-                    aspiration_window.beta(),
-                    &mut pv,
                 );
+                let mut pv = PrincipleVariation::new();
+
+                let mut score: Score;
+                'aspiration_window: loop {
+                    // search the tree, starting at the current depth (starts at 1)
+                    score = self.negamax::<RootNode>(
+                        board,
+                        best_result.depth as ScoreType,
+                        0,
+                        aspiration_window.alpha(),
+                        aspiration_window.beta(),
+                        &mut pv,
+                    );
 
-                if aspiration_window.failed_low(score) {
-                    // fail low, widen the window
-                    aspiration_window.widen_down(score, best_result.depth as ScoreType);
-                } else if aspiration_window.failed_high(score) {
-                    // fail high, widen the window
-                    aspiration_window.widen_up(score, best_result.depth as ScoreType);
-                } else {
-                    // we have a valid score, break the loop
-                    break 'aspiration_window;
+                    let bound = if aspiration_window.failed_low(score) {
+                        // fail low, widen the window
+                        aspiration_window.widen_down(score, best_result.depth as ScoreType);
+                        UciBound::Upperbound
+                    } else if aspiration_window.failed_high(score) {
+                        // fail high, widen the window
+                        aspiration_window.widen_up(score, best_result.depth as ScoreType);
+                        UciBound::Lowerbound
+                    } else {
+                        // we have a valid score, break the loop
+                        break 'aspiration_window;
+                    };
+
+                    // check stop conditions
+                    if self.should_stop_searching() {
+                        // we have to stop searching now, the best result keeps the last full
+                        // iteration, but the line shows what we learned as a bound
+                        if pv.iter().next().is_some() {
+                            lines[line_index] = PvLine {
+                                score,
+                                bound: Some(bound),
+                                pv,
+                            };
+                        }
+                        break 'deepening;
+                    }
                 }
 
-                // check stop conditions
+                // a stopped search can end inside the window with a score that can't be trusted,
+                // keep the lines and the best result of the last full iteration
                 if self.should_stop_searching() {
-                    // we have to stop searching now, use the best result we have
-                    // no score update
                     break 'deepening;
                 }
+
+                lines[line_index] = PvLine {
+                    score,
+                    bound: None,
+                    pv,
+                };
             }
 
+            // keep the best line first, the sort is stable so equal lines keep their order
+            lines.sort_by_key(|line| std::cmp::Reverse(line.score));
+            self.root_moves.sort();
+
             // update the best result
-            best_result.score = score;
-            best_result.best_move = self
-                .transposition_table
-                .get_entry(board.zobrist_hash())
-                .map(|e| e.board_move);
-            best_result.pv = pv;
+            best_result.score = lines[0].score;
+            // the root moves still know the best move if the line has no PV
+            let best_pv = if lines[0].pv.iter().next().is_some() {
+                Some(&lines[0].pv)
+            } else {
+                self.root_moves.best().map(|root_move| &root_move.pv)
+            };
+            if let Some(pv) = best_pv {
+                best_result.best_move = pv.iter().next().copied();
+                best_result.pv = pv.clone();
+            }
+            if let Some(best_move) = best_result.best_move {
+                let best_move_nodes = self
+                    .root_moves
+                    .find(&best_move)
+                    .map_or(0, |root_move| root_move.nodes);
+                self.time_manager
+                    .update(best_move, best_result.score, best_move_nodes, self.nodes);
+            }
 
             // verify the PV as a sanity check, but only in debug
             debug_assert!(
-                self.verify_pv_moves(&best_result.pv, board).is_ok(),
+                lines
+                    .iter()
+                    .all(|line| self.verify_pv_moves(&line.pv, board).is_ok()),
                 "PV invalid"
             );
 
             if Log::INFO {
                 // send UCI info
-                self.send_info(
-                    best_result.depth,
-                    self.nodes,
-                    best_result.score,
-                    (self.nodes as f32 / self.parameters.start_time.elapsed().as_secs_f32())
-                        .trunc(),
-                    self.parameters.start_time.elapsed().as_millis() as u64,
-                    &best_result.pv,
-                );
+                self.send_lines(best_result.depth, &lines);
             }
 
             // increment depth for next iteration
             best_result.depth += 1;
+
+            // `go mate` searches are done once the mate is proven
+            if self.is_requested_mate(best_result.score) {
+                break 'deepening;
+            }
         }
 
+        // the next search starts without any excluded root moves
+        self.excluded_root_moves.clear();
+        self.root_move_hint = None;
+
         // update total nodes for the current search
         best_result.nodes = self.nodes;
 
         if Log::INFO {
             // Send one last info line with the final result
-            // send UCI info
-            self.send_info(
-                best_result.depth,
-                self.nodes,
-                best_result.score,
-                (self.nodes as f32 / self.parameters.start_time.elapsed().as_secs_f32()).trunc(),
-                self.parameters.start_time.elapsed().as_millis() as u64,
-                &best_result.pv,
-            );
+            self.send_lines(best_result.depth, &lines);
         }
 
         // return our best result so far
@@ -422,7 +700,7 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
         board: &mut Board,
         mut depth: ScoreType,
         ply: ScoreType,
-        alpha: Score,
+        mut alpha: Score,
         beta: Score,
         pv: &mut PrincipleVariation,
     ) -> Score
@@ -431,45 +709,93 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
     {
         // increment node count
         self.nodes += 1;
+
+        // Upcoming repetition: if we can move back to a position from earlier in the search, the
+        // draw can be claimed before it happens
+        if ply > 0 && board.upcoming_repetition(ply as usize) {
+            let draw_score = self.draw_score(board);
+            if draw_score > alpha {
+                alpha = draw_score;
+                if alpha >= beta {
+                    return alpha;
+                }
+            }
+        }
+
         let alpha_original = alpha;
         let mut alpha_use = alpha;
 
         if depth <= 0 {
-            return self.quiescence::<Node>(board, alpha, beta, pv);
+            return self.quiescence::<Node>(board, ply, alpha, beta, pv);
+        }
+
+        // don't search past the end of the search stack
+        if ply >= MAX_PLY {
+            return self.eval.eval(board);
         }
 
         let mut local_pv = PrincipleVariation::new();
         // clear the current PV because this is a new position
         pv.clear();
 
+        // a node searched with an excluded move is a reduced version of the same position, so it
+        // must not use or overwrite the transposition table entry of the full search
+        let excluded_move = self
+            .search_stack
+            .at(ply)
+            .and_then(|entry| entry.excluded_move);
+
         // Transposition Table Cutoffs: https://www.chessprogramming.org/Transposition_Table#Transposition_Table_Cutoffs
         // Check if we have a transposition table entry and if we can return early
-        let tt_move =
-            match self
-                .transposition_table
-                .probe::<Node>(depth, board.zobrist_hash(), alpha, beta)
-            {
-                ttable::ProbeResult::CutOff(entry) => {
-                    // we have a cutoff, so return the score, but only in a non-PV node
-                    self.nodes += 1;
-                    if !Node::PV {
-                        return entry.score;
-                    }
-                    Some(entry.board_move)
+        let probe_result = if excluded_move.is_none() {
+            self.transposition_table
+                .probe::<Node>(depth, ply, board.zobrist_hash(), alpha, beta)
+        } else {
+            ttable::ProbeResult::Empty
+        };
+        let tt_entry = match probe_result {
+            ttable::ProbeResult::CutOff(entry) => {
+                // we have a cutoff, so return the score, but only in a non-PV node
+                self.nodes += 1;
+                if !Node::PV {
+                    return entry.score;
                 }
-                ttable::ProbeResult::Hit(entry) => Some(entry.board_move),
-                ttable::ProbeResult::Empty => None,
-            };
+                Some(entry)
+            }
+            ttable::ProbeResult::Hit(entry) => Some(entry),
+            ttable::ProbeResult::Empty => None,
+        };
+        let tt_packed_move = tt_entry.map(|entry| entry.board_move);
+
+        // record the state of this node for its children and for the pruning below
+        let in_check = board.is_in_check(&self.move_gen);
+        let raw_eval = (!in_check).then(|| self.eval.eval(board));
+        let static_eval = raw_eval.map(|eval| self.correction_history.correct(board, eval));
+        if let Some(entry) = self.search_stack.at_mut(ply) {
+            entry.static_eval = static_eval;
+        }
+
+        // Are we improving on the position we had two plies ago? If that position was in check,
+        // compare with the one two plies before it instead.
+        let improving = static_eval.is_some_and(|eval| {
+            self.search_stack
+                .previous_static_eval(ply, 2)
+                .or_else(|| self.search_stack.previous_static_eval(ply, 4))
+                .is_none_or(|previous| eval > previous)
+        });
 
         // Internal Iterative Reductions: https://www.chessprogramming.org/Internal_Iterative_Reductions
         // If no tt entry was found, searching it will be very costly, so we reduce the depth. This is
         // working under the assumption that the position is likely not important.
-        if tt_move.is_none() && depth >= IIR_MIN_DEPTH {
+        if tt_packed_move.is_none() && excluded_move.is_none() && depth >= IIR_MIN_DEPTH {
             depth -= IIR_DEPTH_REDUCTION;
         }
 
         // can we prune the current node with something other than TT?
-        if let Some(score) = self.pruned_score::<Node>(board, depth, ply, beta, &mut local_pv) {
+        if excluded_move.is_none()
+            && let Some(score) =
+                self.pruned_score::<Node>(board, depth, ply, alpha, beta, improving, &mut local_pv)
+        {
             return score;
         }
 
@@ -478,20 +804,57 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
         let mut order_list = ArrayVec::<MoveOrder, MAX_MOVE_LIST_SIZE>::new();
         self.move_gen.generate_legal_moves(board, &mut move_list);
 
+        // the root only searches the moves given with `searchmoves`, and lines of a MultiPV
+        // search skip the root moves of the lines above them
+        let restricted_root = Node::ROOT
+            && (!self.parameters.search_moves.is_empty() || !self.excluded_root_moves.is_empty());
+        if restricted_root {
+            move_list.retain(|mv| {
+                self.root_moves.contains(mv) && !self.excluded_root_moves.contains(mv)
+            });
+        }
+
         // do we have moves?
         if move_list.is_empty() {
-            return if board.is_in_check(&self.move_gen) {
+            return if in_check {
                 -Score::MATE + ply
             } else {
-                Score::DRAW
+                self.draw_score(board)
             };
         }
 
+        // the TT only stores a packed move, find the full move in the legal moves. The TT move of
+        // a restricted root may not be searched, so use the move from the last iteration instead.
+        let tt_move = if restricted_root {
+            self.root_move_hint
+        } else {
+            tt_packed_move.and_then(|mv| mv.resolve(move_list.iter()))
+        };
+
+        // countermoves are indexed by the move our opponent just made
+        let previous_move = board.last_move().filter(|mv| !mv.is_null_move());
+        let previous_side = Side::opposite(board.side_to_move());
+        let countermove = previous_move.and_then(|prev| {
+            self.countermove_table
+                .get(previous_side, prev.piece(), prev.to())
+        });
+
+        let stm = board.side_to_move();
         let classify_res = MoveOrder::classify_all(
-            board.side_to_move(),
+            board,
             move_list.as_slice(),
             &tt_move,
-            self.history_table,
+            self.search_stack
+                .at(ply)
+                .map_or(&[], |entry| entry.killers.as_slice()),
+            &countermove,
+            &|mv: &Move| {
+                if mv.is_capture() {
+                    self.capture_history.get(mv)
+                } else {
+                    self.quiet_history(stm, ply, mv)
+                }
+            },
             &mut order_list,
         );
 
@@ -501,22 +864,37 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
         // sort moves by MVV/LVA
         let move_iter = InplaceIncrementalSort::new(move_list.as_mut_slice(), &mut order_list);
 
+        let tt_move_is_capture = tt_move.is_some_and(|mv| mv.is_capture());
+
         // Really "bad" initial score
         let mut best_score = -Score::INF;
         let mut best_move = tt_move;
+        // moves that were actually searched, pruned and excluded moves are left out
+        let mut quiets_searched = Vec::new();
+        let mut captures_searched = Vec::new();
 
         // Loop through all moves
-        for (i, mv) in move_iter.into_iter().enumerate() {
+        for (i, (mv, order)) in move_iter.into_iter().enumerate() {
+            if excluded_move == Some(mv) {
+                continue;
+            }
+
             // Calculate the LMR reduction and depth which will be used later in FP
-            let lmr_table_value = self.lmr_table.at(depth as usize, i);
+            let lmr_table = if mv.is_quiet() {
+                &self.lmr_table
+            } else {
+                &self.capture_lmr_table
+            };
+            let lmr_table_value = lmr_table.at(depth as usize, i);
             let base_reduction = if let Some(table_val) = lmr_table_value {
                 *table_val
             } else {
                 1f64
             };
-            let lmr_reduction = (1f64 + base_reduction).floor() as i16;
-            let _lmr_depth = depth.saturating_sub(lmr_reduction);
-            let is_in_check = board.is_in_check(&self.move_gen);
+            // reduce more when the position is getting worse
+            let lmr_reduction =
+                (1f64 + base_reduction).floor() as i16 + ScoreType::from(!improving);
+            let lmr_depth = depth.saturating_sub(lmr_reduction);
             let is_root = Node::ROOT;
             let is_pv = Node::PV;
 
@@ -525,49 +903,207 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
             // LMP - Late Move Pruning
             // We assume our move ordering is just too good, so if we're under a certain depth
             // and have made more than a certain number of moves, we can assume that later moves
-            // won't be as good, so we prune them.
-            if !is_root && !is_pv && !is_in_check && !best_score.mated() {
-                let min_lmp_moves =
-                    LMP_MIN_THRESHOLD_DEPTH as usize + depth as usize * depth as usize;
+            // won't be as good, so we prune them. If we are not improving, we prune twice as early.
+            if !is_root && !is_pv && !in_check && !best_score.mated() {
+                let min_lmp_moves = (LMP_MIN_THRESHOLD_DEPTH as usize
+                    + depth as usize * depth as usize)
+                    / (2 - usize::from(improving));
                 if i >= min_lmp_moves {
                     break;
                 }
             }
 
+            let quiet_history = if mv.is_quiet() {
+                self.quiet_history(stm, ply, &mv)
+            } else {
+                0
+            };
+
+            if let Some(static_eval) = static_eval
+                && !is_root
+                && !is_pv
+                && !best_score.mated()
+                && !gives_direct_check(board, &mv)
+            {
+                // Futility pruning: https://www.chessprogramming.org/Futility_Pruning
+                // If the static evaluation is so far below alpha that even a generous margin for
+                // the reduced depth doesn't reach it, this quiet move is unlikely to raise alpha.
+                if mv.is_quiet()
+                    && lmr_depth <= MAX_FP_DEPTH
+                    && static_eval + FP_BASE_MARGIN + FP_MARGIN * lmr_depth <= alpha_use
+                {
+                    continue;
+                }
+
+                // History pruning
+                // Quiet moves that have failed low many times before are skipped at low depths.
+                if mv.is_quiet()
+                    && depth <= MAX_HISTORY_PRUNING_DEPTH
+                    && quiet_history < -HISTORY_PRUNING_MARGIN * depth as LargeScoreType
+                {
+                    continue;
+                }
+
+                // SEE pruning
+                // Skip moves that lose too much material at low depths, with a larger allowance
+                // for captures as they already won some material back.
+                if depth <= MAX_SEE_PRUNING_DEPTH {
+                    let threshold = if mv.is_quiet() {
+                        -SEE_QUIET_MARGIN * depth as i32
+                    } else {
+                        -SEE_CAPTURE_MARGIN * (depth * depth) as i32
+                    };
+                    if !board.see(&mv, threshold) {
+                        continue;
+                    }
+                }
+            }
+
+            // Singular extensions: https://www.chessprogramming.org/Singular_Extensions
+            // If the TT move beats a lower bound by a margin while every other move fails to reach
+            // that bound in a reduced search, the TT move is the only good move here and is
+            // searched one ply deeper.
+            let mut extension = 0;
+            if let Some(entry) = tt_entry
+                && !Node::ROOT
+                && excluded_move.is_none()
+                && tt_move == Some(mv)
+                && depth >= SE_MIN_DEPTH
+                && entry.flag == ttable::EntryFlag::LowerBound
+                && entry.depth as ScoreType >= depth - SE_TT_DEPTH_MARGIN
+                && !entry.score.is_mate()
+            {
+                let singular_beta = entry.score - SE_MARGIN * depth;
+                let singular_depth = (depth - 1) / 2;
+
+                if let Some(stack_entry) = self.search_stack.at_mut(ply) {
+                    stack_entry.excluded_move = Some(mv);
+                }
+                let singular_score = self.negamax::<NonPvNode>(
+                    board,
+                    singular_depth,
+                    ply,
+                    singular_beta - 1,
+                    singular_beta,
+                    &mut local_pv,
+                );
+                if let Some(stack_entry) = self.search_stack.at_mut(ply) {
+                    stack_entry.excluded_move = None;
+                }
+
+                if singular_score < singular_beta {
+                    extension = 1;
+                } else if singular_beta >= beta {
+                    // Multi-cut: https://www.chessprogramming.org/Multi-Cut
+                    // at least one other move also beats beta, so this node is very likely to fail high
+                    return singular_beta;
+                }
+            }
+
             // local PV is for each node below this one is different when we call negamax recursively
             // so we have to clear it
             local_pv.clear();
 
             // make the move
+            if let Some(entry) = self.search_stack.at_mut(ply) {
+                entry.current_move = Some(mv);
+            }
+            let nodes_before = self.nodes;
             board.make_move_unchecked(&mv).unwrap();
-            let mut score = Score::DRAW;
+            if mv.is_quiet() {
+                quiets_searched.push(mv);
+            } else if mv.is_capture() {
+                captures_searched.push(mv);
+            }
+
+            // Check extensions: https://www.chessprogramming.org/Check_Extensions
+            // search moves that give check one ply deeper so we don't miss forcing lines
+            let gives_check = board.is_in_check(&self.move_gen);
+            if extension == 0 && gives_check {
+                extension = 1;
+            }
 
             // Don't bother searching drawn positions
-            if !board.is_draw() {
-                score =
-                // Principal Variation Search (PVS)
-                if Node::PV && i == 0 {
-                    -self.negamax::<PvNode>(board, depth - 1, ply + 1, -beta, -alpha_use, &mut local_pv)
-                } else {
-                    let reduction = if mv.is_quiet() &&  depth >= 3 && board.full_move_number() >= 3 {
-                        lmr_reduction
+            let score = if board.is_draw_in_search((ply + 1) as usize) {
+                -self.draw_score(board)
+            }
+            // Principal Variation Search (PVS)
+            else if Node::PV && i == 0 {
+                -self.negamax::<PvNode>(
+                    board,
+                    depth + extension - 1,
+                    ply + 1,
+                    -beta,
+                    -alpha_use,
+                    &mut local_pv,
+                )
+            } else {
+                // root moves, moves that give check and captures that don't lose material are not
+                // reduced, so forcing lines are searched to their full depth
+                let reducible = !Node::ROOT
+                    && !gives_check
+                    && (mv.is_quiet() || matches!(order, MoveOrder::BadCapture(..)));
+                let reduction = if depth >= LMR_MIN_DEPTH && i > 0 && reducible {
+                    // reduce moves with a good history less, and moves with a bad history more
+                    let history_adjustment = if mv.is_quiet() {
+                        // quiets are less likely to be best when the TT move is a capture
+                        ScoreType::from(tt_move_is_capture)
+                            - (quiet_history / LMR_HISTORY_DIVISOR) as ScoreType
                     } else {
-                        1
+                        -(self.capture_history.get(&mv) / LMR_CAPTURE_HISTORY_DIVISOR) as ScoreType
                     };
-                    // search with a null window
-                    let temp_score = -self.negamax::<NonPvNode>(board, depth - reduction, ply + 1, -alpha_use - 1, -alpha_use, &mut local_pv);
-                    // if it fails, we need to do a full re-search
-                    if temp_score > alpha_use && temp_score < beta {
-                        -self.negamax::<NonPvNode>(board, depth - 1, ply + 1, -beta, -alpha_use, &mut local_pv)
-                    }
-                    else {
-                        temp_score
-                    }
+                    // reduce less in PV nodes
+                    (lmr_reduction + history_adjustment - ScoreType::from(Node::PV)).max(1)
+                } else {
+                    1
                 };
-            }
+                // search with a null window
+                let mut temp_score = -self.negamax::<NonPvNode>(
+                    board,
+                    depth + extension - reduction,
+                    ply + 1,
+                    -alpha_use - 1,
+                    -alpha_use,
+                    &mut local_pv,
+                );
+                // a reduced search that beats alpha has to be verified at full depth
+                if reduction > 1 && temp_score > alpha_use {
+                    temp_score = -self.negamax::<NonPvNode>(
+                        board,
+                        depth + extension - 1,
+                        ply + 1,
+                        -alpha_use - 1,
+                        -alpha_use,
+                        &mut local_pv,
+                    );
+                }
+                // if it fails, we need to do a full re-search
+                if temp_score > alpha_use && temp_score < beta {
+                    -self.negamax::<NonPvNode>(
+                        board,
+                        depth + extension - 1,
+                        ply + 1,
+                        -beta,
+                        -alpha_use,
+                        &mut local_pv,
+                    )
+                } else {
+                    temp_score
+                }
+            };
 
             // undo the move
             board.unmake_move().unwrap();
+            if Node::ROOT {
+                // only moves that raised alpha have an exact score or a lower bound, and the
+                // score of a stopped search can't be trusted
+                let raised_alpha = score > alpha_use && !self.should_stop_searching();
+                self.root_moves.update(
+                    &mv,
+                    self.nodes - nodes_before,
+                    raised_alpha.then_some((score, &local_pv)),
+                );
+            }
 
             // check the results
             if score > best_score {
@@ -582,26 +1118,36 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
                 alpha_use = alpha_use.max(best_score);
                 // Did we fail high?
                 if alpha_use >= beta {
-                    // update history table for quiets
+                    // calculate history bonus
+                    let bonus = history_table::calculate_bonus_for_depth(depth) as LargeScoreType;
+
+                    // update killers, countermoves and the history table for quiets
                     if mv.is_quiet() {
-                        // calculate history bonus
-                        let bonus = history_table::calculate_bonus_for_depth(depth);
-                        self.history_table.update(
-                            board.side_to_move(),
-                            mv.piece(),
-                            mv.to(),
-                            bonus as LargeScoreType,
-                        );
+                        if let Some(entry) = self.search_stack.at_mut(ply) {
+                            entry.killers.update(mv);
+                        }
+                        if let Some(prev) = previous_move {
+                            self.countermove_table.update(
+                                previous_side,
+                                prev.piece(),
+                                prev.to(),
+                                mv,
+                            );
+                        }
+
+                        self.update_quiet_history(stm, ply, &mv, bonus);
 
                         // apply a penalty to all quiets searched so far
-                        for mv in move_list.iter().take(i).filter(|mv| mv.is_quiet()) {
-                            self.history_table.update(
-                                board.side_to_move(),
-                                mv.piece(),
-                                mv.to(),
-                                -bonus as LargeScoreType,
-                            );
+                        for searched in quiets_searched.iter().filter(|&&searched| searched != mv) {
+                            self.update_quiet_history(stm, ply, searched, -bonus);
                         }
+                    } else if mv.is_capture() {
+                        self.capture_history.update(&mv, bonus);
+                    }
+
+                    // apply a penalty to all captures searched so far, they did not cause a cutoff
+                    for searched in captures_searched.iter().filter(|&&searched| searched != mv) {
+                        self.capture_history.update(searched, -bonus);
                     }
                     break;
                 }
@@ -613,6 +1159,10 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
             }
         }
 
+        if excluded_move.is_some() || restricted_root {
+            return best_score;
+        }
+
         // store the best move in the transposition table
         let flag = if best_score <= alpha_original {
             ttable::EntryFlag::UpperBound
@@ -622,11 +1172,23 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
             ttable::EntryFlag::Exact
         };
 
+        // learn how far the static evaluation was from the search result, unless the best move
+        // is a capture (which the static evaluation can't see) or the bound says nothing about it
+        if let (Some(raw_eval), Some(static_eval)) = (raw_eval, static_eval)
+            && !best_move.is_some_and(|mv| mv.is_capture())
+            && !best_score.is_mate()
+            && !(flag == ttable::EntryFlag::LowerBound && best_score <= static_eval)
+            && !(flag == ttable::EntryFlag::UpperBound && best_score >= static_eval)
+        {
+            let error = LargeScoreType::from(best_score.0) - LargeScoreType::from(raw_eval.0);
+            self.correction_history.update(board, depth, error);
+        }
+
         self.transposition_table
             .store_entry(TranspositionTableEntry::new(
                 board.zobrist_hash(),
                 depth as u8,
-                best_score,
+                best_score.to_tt(ply),
                 flag,
                 best_move.unwrap(),
             ));
@@ -634,39 +1196,97 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
         best_score
     }
 
+    /// Returns the history score of a quiet move made by `stm` at `ply`. This combines the main
+    /// history table with the continuation histories of the moves leading to this node.
+    fn quiet_history(&self, stm: Side, ply: ScoreType, mv: &Move) -> LargeScoreType {
+        let continuation_score: LargeScoreType = self
+            .continuation_history
+            .iter()
+            .zip(CONTINUATION_HISTORY_PLIES)
+            .filter_map(|(history, offset)| {
+                self.search_stack
+                    .previous_move(ply, offset)
+                    .map(|previous| history.get(stm, &previous, mv.piece(), mv.to()))
+            })
+            .sum();
+
+        self.history_table.get(stm, mv.piece(), mv.to()) + continuation_score
+    }
+
+    /// Applies `bonus` to the main and continuation histories of a quiet move made by `stm` at `ply`.
+    fn update_quiet_history(
+        &mut self,
+        stm: Side,
+        ply: ScoreType,
+        mv: &Move,
+        bonus: LargeScoreType,
+    ) {
+        self.history_table.update(stm, mv.piece(), mv.to(), bonus);
+
+        for (history, offset) in self
+            .continuation_history
+            .iter_mut()
+            .zip(CONTINUATION_HISTORY_PLIES)
+        {
+            if let Some(previous) = self.search_stack.previous_move(ply, offset) {
+                history.update(stm, &previous, mv.piece(), mv.to(), bonus);
+            }
+        }
+    }
+
     /// Checks to see if the current node can be pruned. If it can, returns the score. Otherwise returns None.
     ///
     /// # Arguments
     ///
     /// - `board` - The current board state.
     /// - `depth` - The current depth.
+    /// - `ply` - The current ply.
+    /// - `alpha` - The current alpha value.
     /// - `beta` - The current beta value.
+    /// - `improving` - Whether the static evaluation improved on the one from two plies ago.
     ///
     /// # Returns
     ///
     /// The score of the position if it can be pruned, otherwise None.
+    #[allow(clippy::too_many_arguments)]
     fn pruned_score<Node: NodeType>(
         &mut self,
-        board: &Board,
+        board: &mut Board,
         depth: ScoreType,
         ply: ScoreType,
+        alpha: Score,
         beta: Score,
+        improving: bool,
         local_pv: &mut PrincipleVariation,
     ) -> Option<Score> {
-        // no pruning if we are in check or if we are in a PV node
-        if board.is_in_check(&self.move_gen) || Node::PV {
+        // no pruning if we are in a PV node
+        if Node::PV {
             return None;
         }
 
-        let static_eval = self.eval.eval(board);
+        // no static eval means we are in check, so don't prune
+        let static_eval = self.search_stack.at(ply)?.static_eval?;
         // Reverse futility pruning
         // https://cosmo.tardis.ac/files/2023-02-20-viri-wiki.html
         // https://www.chessprogramming.org/Reverse_Futility_Pruning
         // If the static evaluation is very high and beats beta by a depth-dependent margin, we can prune the move.
-        if depth <= MAX_RFP_DEPTH && static_eval - RFP_MARGIN * depth > beta {
+        // The margin is smaller when we are improving, as the position is more likely to hold.
+        let rfp_margin = RFP_MARGIN * (depth - ScoreType::from(improving));
+        if depth <= MAX_RFP_DEPTH && static_eval - rfp_margin > beta {
             return Some(static_eval);
         }
 
+        // Razoring
+        // https://www.chessprogramming.org/Razoring
+        // If the static evaluation is far below alpha at a shallow depth, only a tactical move could
+        // save us. Verify with a quiescence search and prune if it still fails low.
+        if depth <= MAX_RAZORING_DEPTH && static_eval + RAZORING_MARGIN * depth < alpha {
+            let score = self.quiescence::<NonPvNode>(board, ply, alpha, alpha + 1, local_pv);
+            if score <= alpha {
+                return Some(score);
+            }
+        }
+
         /*
         Null move pruning
         https://www.chessprogramming.org/Null_Move_Pruning
@@ -684,25 +1304,113 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
         // was the last move null?
         let last_move_was_null = board.last_move().is_some_and(|mv| mv.is_null_move());
 
+        // during a verification search, null moves are disabled for the side being verified
+        let nmp_allowed = ply >= self.nmp_min_ply || board.side_to_move() != self.nmp_side;
+
         if !last_move_was_null
+            && nmp_allowed
             && depth >= NMP_MIN_DEPTH
             && static_eval >= beta
             && sufficient_material
         {
-            let null_move_depth = depth - NMP_DEPTH_REDUCTION - 1;
-            let mut null_board = board.clone();
-            null_board.null_move();
-            let null_score = -self.negamax::<Node>(
-                &mut null_board,
-                null_move_depth,
-                ply + 1,
-                -beta,
-                -beta + 1,
-                local_pv,
-            );
-            null_board.unmake_move().unwrap();
+            // reduce more at higher depths and when we are further ahead
+            let eval_reduction =
+                ((static_eval - beta).0 / NMP_EVAL_REDUCTION_DIVISOR).min(NMP_MAX_EVAL_REDUCTION);
+            let null_move_depth =
+                depth - NMP_DEPTH_REDUCTION - depth / NMP_DEPTH_DIVISOR - eval_reduction - 1;
+            if let Some(entry) = self.search_stack.at_mut(ply) {
+                entry.current_move = None;
+            }
+            board.null_move();
+            let null_score =
+                -self.negamax::<Node>(board, null_move_depth, ply + 1, -beta, -beta + 1, local_pv);
+            board.unmake_move().unwrap();
+
             if null_score >= beta {
-                return Some(null_score);
+                // don't trust mate scores found without making a move
+                let null_score = if null_score.is_mate() {
+                    beta
+                } else {
+                    null_score
+                };
+
+                // at low depths the null move search is trusted as is
+                if depth < NMP_VERIFICATION_DEPTH || self.nmp_min_ply != 0 {
+                    return Some(null_score);
+                }
+
+                // Verification search
+                // In zugzwang, passing is better than any real move. Search the position again at
+                // the reduced depth without null moves for us, and only prune if it still fails high.
+                self.nmp_min_ply = ply + 3 * null_move_depth / 4;
+                self.nmp_side = board.side_to_move();
+                let verified_score = self.negamax::<NonPvNode>(
+                    board,
+                    null_move_depth,
+                    ply,
+                    beta - 1,
+                    beta,
+                    local_pv,
+                );
+                self.nmp_min_ply = 0;
+
+                if verified_score >= beta {
+                    return Some(null_score);
+                }
+            }
+        }
+
+        // ProbCut
+        // https://www.chessprogramming.org/ProbCut
+        // If a good capture beats beta by a margin in a reduced search, a full depth search would
+        // very likely beat beta as well.
+        let probcut_beta = beta + PROBCUT_MARGIN;
+        if depth >= PROBCUT_MIN_DEPTH && !beta.is_mate() {
+            let mut move_list = MoveList::new();
+            self.move_gen.generate_legal_moves(board, &mut move_list);
+
+            // only captures that win enough material to reach the ProbCut beta are worth trying
+            let see_threshold = i32::from((probcut_beta - static_eval).0);
+            let captures = move_list
+                .iter()
+                .filter(|mv| mv.is_capture() && board.see(mv, see_threshold))
+                .copied()
+                .collect::<Vec<_>>();
+
+            for mv in captures {
+                if let Some(entry) = self.search_stack.at_mut(ply) {
+                    entry.current_move = Some(mv);
+                }
+                board.make_move_unchecked(&mv).unwrap();
+
+                // verify with a quiescence search first, as it is much cheaper
+                let mut score = -self.quiescence::<NonPvNode>(
+                    board,
+                    ply + 1,
+                    -probcut_beta,
+                    -probcut_beta + 1,
+                    local_pv,
+                );
+                if score >= probcut_beta {
+                    score = -self.negamax::<NonPvNode>(
+                        board,
+                        depth - PROBCUT_DEPTH_REDUCTION - 1,
+                        ply + 1,
+                        -probcut_beta,
+                        -probcut_beta + 1,
+                        local_pv,
+                    );
+                }
+                board.unmake_move().unwrap();
+
+                // the score of a stopped search can't be trusted
+                if self.should_stop_searching() {
+                    return None;
+                }
+
+                if score >= probcut_beta {
+                    return Some(score);
+                }
             }
         }
 
@@ -726,11 +1434,23 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
     fn quiescence<Node: NodeType>(
         &mut self,
         board: &mut Board,
+        ply: ScoreType,
         alpha: Score,
         beta: Score,
         pv: &mut PrincipleVariation,
     ) -> Score {
-        let standing_eval = self.eval.eval(board);
+        // don't search past the end of the search stack
+        if ply >= MAX_PLY {
+            return self.eval.eval(board);
+        }
+
+        // we can't stand pat when in check, every evasion has to be searched
+        let in_check = board.is_in_check(&self.move_gen);
+        let standing_eval = if in_check {
+            -Score::INF
+        } else {
+            self.eval.eval(board)
+        };
         if standing_eval >= beta {
             return beta;
         }
@@ -744,64 +1464,90 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
         // clear the current PV because this is a new position
         pv.clear();
 
-        // we only want captures here
-        let mut captures = move_list
+        if in_check && move_list.is_empty() {
+            return -Score::MATE + ply;
+        }
+
+        // we only want captures here, unless we have to get out of check
+        let mut moves = move_list
             .iter()
-            .filter(|mv| mv.captured_piece().is_some())
+            .filter(|mv| in_check || mv.captured_piece().is_some())
             .copied()
             .collect::<Vec<_>>();
 
         // no captures
-        if captures.is_empty() {
+        if moves.is_empty() {
             return standing_eval;
         }
 
         // Transposition Table Cutoffs: https://www.chessprogramming.org/Transposition_Table#Transposition_Table_Cutoffs
         // Check if we have a transposition table entry and if we can return early
-        let tt_move =
-            match self
-                .transposition_table
-                .probe::<Node>(0, board.zobrist_hash(), alpha_use, beta)
-            {
-                ttable::ProbeResult::CutOff(entry) => {
-                    // we have a cutoff, so return the score, but only in a non-PV node
-                    if !Node::PV {
-                        return entry.score;
-                    }
-                    Some(entry.board_move)
+        let tt_packed_move = match self.transposition_table.probe::<Node>(
+            0,
+            ply,
+            board.zobrist_hash(),
+            alpha_use,
+            beta,
+        ) {
+            ttable::ProbeResult::CutOff(entry) => {
+                // we have a cutoff, so return the score, but only in a non-PV node
+                if !Node::PV {
+                    return entry.score;
                 }
-                ttable::ProbeResult::Hit(entry) => Some(entry.board_move),
-                ttable::ProbeResult::Empty => None,
-            };
+                Some(entry.board_move)
+            }
+            ttable::ProbeResult::Hit(entry) => Some(entry.board_move),
+            ttable::ProbeResult::Empty => None,
+        };
+        let tt_move = tt_packed_move.and_then(|mv| mv.resolve(moves.iter()));
 
         // sort moves by MVV/LVA
+        let stm = board.side_to_move();
         let classify_res = MoveOrder::classify_all(
-            board.side_to_move(),
-            captures.as_slice(),
+            board,
+            moves.as_slice(),
             &tt_move,
-            self.history_table,
+            &[],
+            &None,
+            &|mv: &Move| {
+                if mv.is_capture() {
+                    self.capture_history.get(mv)
+                } else {
+                    self.history_table.get(stm, mv.piece(), mv.to())
+                }
+            },
             &mut move_order_list,
         );
         // TODO(PT): Should we log a message to the CLI or a log?
         assert!(classify_res.is_ok());
 
-        let captures_slice = captures.as_mut_slice();
-        let move_iter = InplaceIncrementalSort::new(captures_slice, &mut move_order_list);
+        // skip captures that lose material, they are unlikely to raise alpha. Classifying the
+        // moves already ran SEE on them.
+        if !in_check {
+            (moves, move_order_list) = moves
+                .into_iter()
+                .zip(move_order_list)
+                .filter(|(_, order)| !matches!(order, MoveOrder::BadCapture(..)))
+                .unzip();
+        }
+
+        let move_iter = InplaceIncrementalSort::new(moves.as_mut_slice(), &mut move_order_list);
 
         let mut best = standing_eval;
         let mut best_move = tt_move;
         let original_alpha = alpha_use;
 
-        for mv in move_iter.into_iter() {
+        for (mv, _) in move_iter.into_iter() {
             // local PV is for each node below this one is different when we call negamax recursively
             // so we have to clear it
             local_pv.clear();
 
             board.make_move_unchecked(&mv).unwrap();
-            let score = if board.is_draw() {
-                Score::DRAW
+            let score = if board.is_draw_in_search((ply + 1) as usize) {
+                -self.draw_score(board)
             } else {
-                let eval = -self.quiescence::<Node>(board, -beta, -alpha_use, &mut local_pv);
+                let eval =
+                    -self.quiescence::<Node>(board, ply + 1, -beta, -alpha_use, &mut local_pv);
                 self.nodes += 1;
                 eval
             };
@@ -844,7 +1590,7 @@ impl<'a, Log: LogLevel> Search<'a, Log> {
                 .store_entry(TranspositionTableEntry::new(
                     board.zobrist_hash(),
                     0u8,
-                    best,
+                    best.to_tt(ply),
                     flag,
                     bm,
                 ));
@@ -887,24 +1633,46 @@ fn assert_pv_is_legal(
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
+    use std::{
+        str::FromStr,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::Duration,
+    };
 
-    use chess::{board::Board, pieces::ALL_PIECES};
+    use chess::{
+        board::Board, move_generation::MoveGenerator, move_list::MoveList, pieces::ALL_PIECES,
+    };
+    use uci_parser::{UciMove, UciSearchOptions};
 
     use crate::{
+        correction_history::CorrectionHistory,
         evaluation::ByteKnightEvaluation,
-        log_level::LogDebug,
+        history_table::HistoryTable,
+        log_level::{LogDebug, LogNone},
+        node_types::{NonPvNode, PvNode, RootNode},
+        principle_variation::PrincipleVariation,
+        root_moves::RootMoves,
         score::Score,
         search::{Search, SearchParameters},
+        traits::Eval,
         ttable::TranspositionTable,
     };
 
-    use super::LargeScoreType;
+    use super::{LargeScoreType, NMP_VERIFICATION_DEPTH, PROBCUT_MARGIN, should_skip_depth};
 
     fn run_search_tests(test_pairs: &[(&str, &str)], config: SearchParameters) {
-        let mut ttable = TranspositionTable::default();
+        let ttable = TranspositionTable::default();
         let mut history_table = Default::default();
-        let mut search = Search::<LogDebug>::new(&config, &mut ttable, &mut history_table);
+        let mut correction_history = Default::default();
+        let mut search = Search::<LogDebug>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        );
 
         for (fen, expected_move) in test_pairs {
             let mut board = Board::from_fen(fen).unwrap();
@@ -925,9 +1693,15 @@ mod tests {
             ..Default::default()
         };
 
-        let mut ttable = TranspositionTable::default();
+        let ttable = TranspositionTable::default();
         let mut history_table = Default::default();
-        let mut search = Search::<LogDebug>::new(&config, &mut ttable, &mut history_table);
+        let mut correction_history = Default::default();
+        let mut search = Search::<LogDebug>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        );
         let res = search.search(&mut board.clone(), None);
         // b6a7
         assert_eq!(
@@ -945,9 +1719,15 @@ mod tests {
             ..Default::default()
         };
 
-        let mut ttable = Default::default();
+        let ttable = Default::default();
         let mut history_table = Default::default();
-        let mut search = Search::<LogDebug>::new(&config, &mut ttable, &mut history_table);
+        let mut correction_history = Default::default();
+        let mut search = Search::<LogDebug>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        );
         let res = search.search(&mut board, None);
 
         assert_eq!(res.best_move.unwrap().to_long_algebraic(), "b8a8")
@@ -974,6 +1754,453 @@ mod tests {
         run_search_tests(&tests, params);
     }
 
+    #[test]
+    fn excluded_move_is_not_searched() {
+        // a7a8 is the only mating move
+        let mut board = Board::from_fen("6k1/R7/6K1/8/8/8/8/8 w - - 0 1").unwrap();
+        let config = SearchParameters::default();
+        let ttable = TranspositionTable::default();
+        let mut history_table = Default::default();
+        let mut correction_history = Default::default();
+        let mut search = Search::<LogNone>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        );
+
+        let mut move_list = MoveList::new();
+        search.move_gen.generate_legal_moves(&board, &mut move_list);
+        let mating_move = move_list
+            .iter()
+            .find(|mv| mv.to_long_algebraic() == "a7a8")
+            .copied();
+        assert!(mating_move.is_some());
+
+        let mut pv = PrincipleVariation::new();
+        let score = search.negamax::<PvNode>(&mut board, 2, 0, -Score::INF, Score::INF, &mut pv);
+        assert_eq!(score, Score::MATE - 1);
+
+        search.search_stack.at_mut(0).unwrap().excluded_move = mating_move;
+        let ttable_entry = ttable.get_entry(board.zobrist_hash()).map(|e| e.score);
+        let score = search.negamax::<PvNode>(&mut board, 2, 0, -Score::INF, Score::INF, &mut pv);
+        assert!(!score.is_mate());
+        // the search with an excluded move must not overwrite the entry of the full search
+        assert_eq!(
+            ttable.get_entry(board.zobrist_hash()).map(|e| e.score),
+            ttable_entry
+        );
+    }
+
+    #[test]
+    fn razoring_prunes_hopeless_positions() {
+        // white is a queen down with no way to win it back
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/8/q3K3 w - - 0 1").unwrap();
+        let config = SearchParameters::default();
+        let ttable = TranspositionTable::default();
+        let mut history_table = Default::default();
+        let mut correction_history = Default::default();
+        let mut search = Search::<LogNone>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        );
+        search.search_stack.at_mut(0).unwrap().static_eval = Some(search.eval.eval(&board));
+
+        let alpha = Score::DRAW;
+        let mut pv = PrincipleVariation::new();
+        let score =
+            search.pruned_score::<NonPvNode>(&mut board, 1, 0, alpha, alpha + 1, false, &mut pv);
+        assert!(score.is_some_and(|score| score <= alpha));
+    }
+
+    #[test]
+    fn probcut_prunes_winning_captures() {
+        // white is behind on material but exd4 wins the queen
+        let mut board = Board::from_fen("4k3/8/8/8/3q4/4P3/8/R3K3 w - - 0 1").unwrap();
+        let config = SearchParameters::default();
+        let ttable = TranspositionTable::default();
+        let mut history_table = Default::default();
+        let mut correction_history = Default::default();
+        let mut search = Search::<LogNone>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        );
+        let static_eval = search.eval.eval(&board);
+        search.search_stack.at_mut(0).unwrap().static_eval = Some(static_eval);
+
+        let beta = Score::DRAW;
+        assert!(static_eval < beta);
+        let mut pv = PrincipleVariation::new();
+        let score =
+            search.pruned_score::<NonPvNode>(&mut board, 6, 0, beta - 1, beta, false, &mut pv);
+        assert!(score.is_some_and(|score| score >= beta + PROBCUT_MARGIN));
+    }
+
+    #[test]
+    fn verified_null_move_cutoff_restores_board() {
+        // white is so far ahead that passing still beats beta
+        let fen = "4k3/8/8/8/8/8/8/QQQQK3 w - - 0 1";
+        let mut board = Board::from_fen(fen).unwrap();
+        let config = SearchParameters::default();
+        let ttable = TranspositionTable::default();
+        let mut history_table = Default::default();
+        let mut correction_history = Default::default();
+        let mut search = Search::<LogNone>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        );
+        search.search_stack.at_mut(0).unwrap().static_eval = Some(search.eval.eval(&board));
+
+        let beta = Score::DRAW;
+        let mut pv = PrincipleVariation::new();
+        let depth = NMP_VERIFICATION_DEPTH;
+        let score =
+            search.pruned_score::<NonPvNode>(&mut board, depth, 0, beta - 1, beta, true, &mut pv);
+
+        assert!(score.is_some_and(|score| score >= beta));
+        // the null move is made in place, so the board must be back where it started
+        assert_eq!(board.to_fen(), fen);
+        assert_eq!(
+            board.zobrist_hash(),
+            Board::from_fen(fen).unwrap().zobrist_hash()
+        );
+        assert_eq!(search.nmp_min_ply, 0);
+    }
+
+    #[test]
+    fn contempt_scores_draws_for_the_root_side() {
+        let config = SearchParameters {
+            contempt: 50,
+            ..Default::default()
+        };
+        let ttable = TranspositionTable::default();
+        let mut history_table = Default::default();
+        let mut correction_history = Default::default();
+        let mut search = Search::<LogNone>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        );
+        let mut board = Board::default_board();
+        search.root_side = board.side_to_move();
+
+        // a draw is bad for the root side and good for its opponent, give or take the jitter
+        let white_draw = search.draw_score(&board);
+        assert!((white_draw - Score::new(-50)).0.abs() <= 1);
+        board.make_uci_move("e2e4").unwrap();
+        let black_draw = search.draw_score(&board);
+        assert!((black_draw - Score::new(50)).0.abs() <= 1);
+
+        // the jitter only depends on the node count
+        search.nodes += 2;
+        assert_ne!(search.draw_score(&board), black_draw);
+        search.nodes += 2;
+        assert_eq!(search.draw_score(&board), black_draw);
+    }
+
+    #[test]
+    fn node_limited_searches_are_deterministic() {
+        let config = SearchParameters {
+            max_nodes: 20_000,
+            contempt: 30,
+            ..Default::default()
+        };
+        let run = || {
+            let mut board = Board::default_board();
+            let ttable = TranspositionTable::default();
+            let mut history_table = Default::default();
+            let mut correction_history = Default::default();
+            Search::<LogNone>::new(
+                &config,
+                &ttable,
+                &mut history_table,
+                &mut correction_history,
+            )
+            .search(&mut board, None)
+        };
+
+        let first = run();
+        let second = run();
+        assert_eq!(first.best_move, second.best_move);
+        assert_eq!(first.score, second.score);
+        assert_eq!(first.depth, second.depth);
+    }
+
+    #[test]
+    fn multi_pv_lines_skip_earlier_root_moves() {
+        // a7a8 is the only mating move
+        let mut board = Board::from_fen("6k1/R7/6K1/8/8/8/8/8 w - - 0 1").unwrap();
+        let config = SearchParameters::default();
+        let ttable = TranspositionTable::default();
+        let mut history_table = Default::default();
+        let mut correction_history = Default::default();
+        let mut search = Search::<LogNone>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        );
+        let mut move_list = MoveList::new();
+        search.generate_root_moves(&board, &mut move_list);
+        search.root_moves = RootMoves::new(&move_list);
+
+        let mut pv = PrincipleVariation::new();
+        let score = search.negamax::<RootNode>(&mut board, 2, 0, -Score::INF, Score::INF, &mut pv);
+        assert_eq!(score, Score::MATE - 1);
+        let mating_move = *pv.iter().next().unwrap();
+        assert_eq!(mating_move.to_long_algebraic(), "a7a8");
+
+        // the second line has to find a different move, and must not overwrite the first line's
+        // transposition table entry
+        search.excluded_root_moves = vec![mating_move];
+        let score = search.negamax::<RootNode>(&mut board, 2, 0, -Score::INF, Score::INF, &mut pv);
+        assert!(!score.is_mate());
+        assert_ne!(pv.iter().next(), Some(&mating_move));
+        assert_eq!(
+            ttable.get_entry(board.zobrist_hash()).map(|e| e.score),
+            Some(Score::MATE - 1)
+        );
+    }
+
+    #[test]
+    fn multi_pv_search_finds_the_same_best_move() {
+        let mut board = Board::from_fen("6k1/R7/6K1/8/8/8/8/8 w - - 0 1").unwrap();
+        let config = SearchParameters {
+            max_depth: 4,
+            multi_pv: 3,
+            ..Default::default()
+        };
+        let ttable = TranspositionTable::default();
+        let mut history_table = Default::default();
+        let mut correction_history = Default::default();
+        let result = Search::<LogNone>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        )
+        .search(&mut board, None);
+        assert_eq!(result.best_move.unwrap().to_long_algebraic(), "a7a8");
+        assert_eq!(result.score, Score::MATE - 1);
+    }
+
+    #[test]
+    fn stopped_multi_pv_search_reports_a_completed_depth() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        for movetime in [10, 20, 30, 40, 50, 60] {
+            let mut board = Board::from_fen(fen).unwrap();
+            let options = UciSearchOptions {
+                movetime: Some(Duration::from_millis(movetime)),
+                ..Default::default()
+            };
+            let config = SearchParameters {
+                multi_pv: 3,
+                ..SearchParameters::new(&options, &board, Duration::ZERO)
+            };
+            let ttable = TranspositionTable::default();
+            let mut history_table = Default::default();
+            let mut correction_history = Default::default();
+            let result = Search::<LogNone>::new(
+                &config,
+                &ttable,
+                &mut history_table,
+                &mut correction_history,
+            )
+            .search(&mut board, None);
+
+            // the depth of the result is the one that was cut short, the reported move has to come
+            // from the iteration before it, which a search to that depth finds again
+            let completed_depth = result.depth - 1;
+            if completed_depth == 0 {
+                continue;
+            }
+            let config = SearchParameters {
+                max_depth: completed_depth,
+                multi_pv: 3,
+                ..Default::default()
+            };
+            let ttable = TranspositionTable::default();
+            let mut history_table = Default::default();
+            let mut correction_history = Default::default();
+            let expected = Search::<LogNone>::new(
+                &config,
+                &ttable,
+                &mut history_table,
+                &mut correction_history,
+            )
+            .search(&mut board, None);
+            assert_eq!(result.best_move, expected.best_move, "movetime {movetime}");
+            assert_eq!(result.score, expected.score, "movetime {movetime}");
+        }
+    }
+
+    #[test]
+    fn search_moves_restrict_the_root_moves() {
+        // a7a8 mates, but only the two king moves may be searched
+        let mut board = Board::from_fen("6k1/R7/6K1/8/8/8/8/8 w - - 0 1").unwrap();
+        let options = UciSearchOptions {
+            depth: Some(4),
+            searchmoves: vec![
+                UciMove::from_str("g6f6").unwrap(),
+                UciMove::from_str("g6h6").unwrap(),
+                // not legal, ignored
+                UciMove::from_str("g6g7").unwrap(),
+            ],
+            ..Default::default()
+        };
+        let config = SearchParameters::new(&options, &board, Duration::ZERO);
+        assert_eq!(config.search_moves.len(), 2);
+
+        let ttable = TranspositionTable::default();
+        let mut history_table = Default::default();
+        let mut correction_history = Default::default();
+        let result = Search::<LogNone>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        )
+        .search(&mut board, None);
+        let best_move = result.best_move.unwrap().to_long_algebraic();
+        assert!(best_move == "g6f6" || best_move == "g6h6");
+        assert!(!result.score.is_mate());
+    }
+
+    #[test]
+    fn infinite_search_waits_for_stop() {
+        let config = SearchParameters::new(
+            &UciSearchOptions {
+                infinite: true,
+                depth: Some(2),
+                ..Default::default()
+            },
+            &Board::default_board(),
+            Duration::ZERO,
+        );
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let finished = AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            let search = scope.spawn(|| {
+                let mut board = Board::default_board();
+                let ttable = TranspositionTable::default();
+                let mut history_table = Default::default();
+                let mut correction_history = Default::default();
+                let result = Search::<LogNone>::new(
+                    &config,
+                    &ttable,
+                    &mut history_table,
+                    &mut correction_history,
+                )
+                .search(&mut board, Some(stop_flag.clone()));
+                finished.store(true, Ordering::Relaxed);
+                result
+            });
+
+            // the search reaches its maximum depth but must not finish before it is stopped
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(!finished.load(Ordering::Relaxed));
+
+            stop_flag.store(true, Ordering::Relaxed);
+            search.thread().unpark();
+            let result = search.join().unwrap();
+            assert!(result.best_move.is_some());
+        });
+    }
+
+    #[test]
+    fn mate_search_stops_once_mate_is_found() {
+        let mut board = Board::from_fen("6k1/R7/6K1/8/8/8/8/8 w - - 0 1").unwrap();
+        let options = UciSearchOptions {
+            mate: Some(1),
+            ..Default::default()
+        };
+        let config = SearchParameters::new(&options, &board, Duration::ZERO);
+        let ttable = TranspositionTable::default();
+        let mut history_table = Default::default();
+        let mut correction_history = Default::default();
+        let result = Search::<LogNone>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        )
+        .search(&mut board, None);
+
+        assert_eq!(result.best_move.unwrap().to_long_algebraic(), "a7a8");
+        assert_eq!(result.score, Score::MATE - 1);
+        // without a depth limit, only the mate ends the search
+        assert!(result.depth < 5);
+    }
+
+    #[test]
+    fn pondering_ignores_time_limits_until_ponderhit() {
+        let ponder = Arc::new(AtomicBool::new(true));
+        let config = SearchParameters {
+            max_depth: 2,
+            soft_timeout: Duration::ZERO,
+            hard_timeout: Duration::ZERO,
+            ponder: Some(ponder.clone()),
+            ..Default::default()
+        };
+        let finished = AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            let search = scope.spawn(|| {
+                let mut board = Board::default_board();
+                let ttable = TranspositionTable::default();
+                let mut history_table = Default::default();
+                let mut correction_history = Default::default();
+                let result = Search::<LogNone>::new(
+                    &config,
+                    &ttable,
+                    &mut history_table,
+                    &mut correction_history,
+                )
+                .search(&mut board, None);
+                finished.store(true, Ordering::Relaxed);
+                result
+            });
+
+            // the search reaches its maximum depth but has to wait for the ponderhit
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(!finished.load(Ordering::Relaxed));
+
+            ponder.store(false, Ordering::Relaxed);
+            search.thread().unpark();
+            let result = search.join().unwrap();
+            assert!(result.best_move.is_some());
+            assert!(result.depth > 1);
+        });
+    }
+
+    #[test]
+    fn quiescence_finds_mate_when_in_check() {
+        // black is checkmated on the back rank, with no captures available
+        let mut board = Board::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        let config = SearchParameters::default();
+        let ttable = TranspositionTable::default();
+        let mut history_table = Default::default();
+        let mut correction_history = Default::default();
+        let mut search = Search::<LogNone>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        );
+
+        let mut pv = PrincipleVariation::new();
+        let score = search.quiescence::<PvNode>(&mut board, 0, -Score::INF, Score::INF, &mut pv);
+        assert_eq!(score, -Score::MATE);
+    }
+
     #[test]
     fn obvious_captures() {
         let tests = [
@@ -998,9 +2225,15 @@ mod tests {
         let mut board = Board::from_fen(fen).unwrap();
         let config = SearchParameters::default();
 
-        let mut ttable = Default::default();
+        let ttable = Default::default();
         let mut history_table = Default::default();
-        let mut search = Search::<LogDebug>::new(&config, &mut ttable, &mut history_table);
+        let mut correction_history = Default::default();
+        let mut search = Search::<LogDebug>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        );
         let res = search.search(&mut board, None);
         assert!(res.best_move.is_none());
         assert_eq!(res.score, Score::DRAW);
@@ -1016,9 +2249,15 @@ mod tests {
             ..Default::default()
         };
 
-        let mut ttable = Default::default();
+        let ttable = Default::default();
         let mut history_table = Default::default();
-        let mut search = Search::<LogDebug>::new(&config, &mut ttable, &mut history_table);
+        let mut correction_history = Default::default();
+        let mut search = Search::<LogDebug>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        );
         let res = search.search(&mut board, None);
 
         assert!(res.best_move.is_some());
@@ -1033,9 +2272,15 @@ mod tests {
             ..Default::default()
         };
 
-        let mut ttable = Default::default();
+        let ttable = Default::default();
         let mut history_table = Default::default();
-        let mut search = Search::<LogDebug>::new(&config, &mut ttable, &mut history_table);
+        let mut correction_history = Default::default();
+        let mut search = Search::<LogDebug>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        );
         let res = search.search(&mut board, None);
         assert!(res.best_move.is_some());
         println!("{}", res.best_move.unwrap().to_long_algebraic());
@@ -1050,9 +2295,15 @@ mod tests {
             ..Default::default()
         };
 
-        let mut ttable = Default::default();
+        let ttable = Default::default();
         let mut history_table = Default::default();
-        let mut search = Search::<LogDebug>::new(&config, &mut ttable, &mut history_table);
+        let mut correction_history = Default::default();
+        let mut search = Search::<LogDebug>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        );
         let res = search.search(&mut board, None);
         assert!(res.best_move.is_some());
         println!("{}", res.best_move.unwrap().to_long_algebraic());
@@ -1110,9 +2361,15 @@ mod tests {
         for fen in TEST_FENS {
             let mut board = Board::from_fen(fen).unwrap();
 
-            let mut ttable = Default::default();
+            let ttable = Default::default();
             let mut history_table = Default::default();
-            let mut search = Search::<LogDebug>::new(&config, &mut ttable, &mut history_table);
+            let mut correction_history = Default::default();
+            let mut search = Search::<LogDebug>::new(
+                &config,
+                &ttable,
+                &mut history_table,
+                &mut correction_history,
+            );
             let res = search.search(&mut board, None);
 
             assert!(res.best_move.is_some());
@@ -1159,13 +2416,142 @@ mod tests {
             ..Default::default()
         };
 
-        let mut ttable = Default::default();
+        let ttable = Default::default();
         let mut history_table = Default::default();
-        let mut search = Search::<LogDebug>::new(&config, &mut ttable, &mut history_table);
+        let mut correction_history = Default::default();
+        let mut search = Search::<LogDebug>::new(
+            &config,
+            &ttable,
+            &mut history_table,
+            &mut correction_history,
+        );
         let res = search.search(&mut board, None);
 
         assert!(res.best_move.is_some());
         let mv = res.best_move.unwrap();
         println!("{}", mv.to_long_algebraic());
     }
+
+    const MAX_TEST_DEPTH: u8 = 32;
+
+    #[test]
+    fn helper_threads_stagger_depths() {
+        // the main thread searches every depth
+        assert!((1..=MAX_TEST_DEPTH).all(|depth| !should_skip_depth(0, depth)));
+
+        for thread_id in 1..=16 {
+            // helpers skip some depths, but never all of them
+            let searched = (1..=MAX_TEST_DEPTH)
+                .filter(|depth| !should_skip_depth(thread_id, *depth))
+                .count();
+            assert!(searched > 0);
+            assert!(searched < MAX_TEST_DEPTH as usize);
+        }
+
+        // neighbouring helpers should not follow the same pattern
+        let pattern = |thread_id| {
+            (1..=MAX_TEST_DEPTH)
+                .map(|depth| should_skip_depth(thread_id, depth))
+                .collect::<Vec<_>>()
+        };
+        assert_ne!(pattern(1), pattern(2));
+    }
+
+    #[test]
+    fn lazy_smp_threads_share_transposition_table() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N1Q2p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let config = SearchParameters {
+            max_depth: 6,
+            ..Default::default()
+        };
+        let ttable = TranspositionTable::default();
+        let helper_stop = Arc::new(AtomicBool::new(false));
+
+        let result = std::thread::scope(|scope| {
+            let helpers = (1..4)
+                .map(|thread_id| {
+                    let helper_params = config.for_helper(thread_id);
+                    let ttable = &ttable;
+                    let flag = helper_stop.clone();
+                    scope.spawn(move || {
+                        let mut history = HistoryTable::default();
+                        let mut correction_history = CorrectionHistory::default();
+                        let mut board = Board::from_fen(fen).unwrap();
+                        Search::<LogNone>::new(
+                            &helper_params,
+                            ttable,
+                            &mut history,
+                            &mut correction_history,
+                        )
+                        .search(&mut board, Some(flag));
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let mut history = HistoryTable::default();
+            let mut correction_history = CorrectionHistory::default();
+            let mut board = Board::from_fen(fen).unwrap();
+            let result =
+                Search::<LogNone>::new(&config, &ttable, &mut history, &mut correction_history)
+                    .search(&mut board, None);
+            helper_stop.store(true, Ordering::Relaxed);
+            helpers.into_iter().for_each(|h| h.join().unwrap());
+            result
+        });
+
+        let board = Board::from_fen(fen).unwrap();
+        assert!(result.best_move.is_some());
+        assert!(board.is_legal(&result.best_move.unwrap(), &MoveGenerator::new()));
+        assert!(ttable.fullness() > 0.0);
+    }
+
+    #[test]
+    fn tt_mate_scores_are_ply_adjusted() {
+        // mate-in-N positions from the bench suite
+        let tests = [
+            ("3k3B/7p/p1Q1p3/2n5/6P1/K3b3/PP5q/R7 w - - 0 1", 1),
+            ("r3k1nr/p1p2p1p/2pP4/8/7q/7b/PPPP3P/RNBQ2KR b kq - 0 1", 1),
+            ("1B1Q1R2/8/qNrn3p/2p1rp2/Rn3k1K/8/5P2/bbN4B w - - 0 1", 2),
+            ("3q4/pp6/6p1/3Pp2k/1Q3p2/4r2P/P5RK/6R1 b - - 0 1", 2),
+            ("5K1k/6R1/8/3b2P1/5p2/p6p/q7/8 w - - 0 1", 3),
+            ("2q3k1/1p4pp/3R1r2/p2bQ3/P7/1N2B3/1PP3rP/R3K3 b - - 0 1", 3),
+        ];
+
+        let config = SearchParameters {
+            max_depth: 8,
+            ..Default::default()
+        };
+
+        for (fen, moves_to_mate) in tests {
+            let expected = Score::MATE - (2 * moves_to_mate - 1);
+            let ttable = TranspositionTable::default();
+            let mut history_table = Default::default();
+            let mut correction_history = Default::default();
+            let mut search = Search::<LogNone>::new(
+                &config,
+                &ttable,
+                &mut history_table,
+                &mut correction_history,
+            );
+
+            let mut board = Board::from_fen(fen).unwrap();
+            let result = search.search(&mut board, None);
+            assert_eq!(result.score, expected, "{fen}");
+
+            if moves_to_mate > 1 {
+                // search the position two plies further down the PV first, so that its mate
+                // scores are stored relative to that position
+                let mut child = board.clone();
+                result.pv.iter().take(2).for_each(|mv| {
+                    child.make_move_unchecked(mv).unwrap();
+                });
+                let child_result = search.search(&mut child, None);
+                assert_eq!(child_result.score, expected + 2, "{fen}");
+            }
+
+            // searching the root again has to adjust those scores by the ply they are found at
+            let result = search.search(&mut board, None);
+            assert_eq!(result.score, expected, "{fen}");
+        }
+    }
 }
@@ -0,0 +1,153 @@
+// Part of the byte-knight project.
+// Author: Paul Tsouchlos (ptsouchlos) (developer.paul.123@gmail.com)
+// GNU General Public License v3.0 or later
+// https://www.gnu.org/licenses/gpl-3.0-standalone.html
+
+use chess::moves::Move;
+
+use crate::{
+    defs::MAX_DEPTH,
+    killer_moves_table::KillerMoves,
+    score::{Score, ScoreType},
+};
+
+/// Information about a single node on the current search path.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SearchStackEntry {
+    /// The move currently being searched from this node. `None` for null moves.
+    pub current_move: Option<Move>,
+    /// Static evaluation of the node. `None` when the side to move is in check, so this also
+    /// tells whether the node was in check.
+    pub static_eval: Option<Score>,
+    /// Quiet moves that caused a beta cutoff at this ply.
+    pub killers: KillerMoves,
+    /// Move that is skipped when searching this node, used to verify that another move is
+    /// singular.
+    pub excluded_move: Option<Move>,
+}
+
+/// Per-ply information about the nodes on the current search path, indexed by ply.
+pub(crate) struct SearchStack {
+    entries: [SearchStackEntry; MAX_DEPTH as usize],
+}
+
+impl SearchStack {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: [SearchStackEntry::default(); MAX_DEPTH as usize],
+        }
+    }
+
+    pub(crate) fn at(&self, ply: ScoreType) -> Option<&SearchStackEntry> {
+        usize::try_from(ply)
+            .ok()
+            .and_then(|ply| self.entries.get(ply))
+    }
+
+    pub(crate) fn at_mut(&mut self, ply: ScoreType) -> Option<&mut SearchStackEntry> {
+        usize::try_from(ply)
+            .ok()
+            .and_then(|ply| self.entries.get_mut(ply))
+    }
+
+    /// Returns the move that was made `offset` plies before the node at `ply`. An offset of `1`
+    /// is the move that led to the node.
+    pub(crate) fn previous_move(&self, ply: ScoreType, offset: ScoreType) -> Option<Move> {
+        self.at(ply - offset).and_then(|entry| entry.current_move)
+    }
+
+    /// Returns the static evaluation of the node `offset` plies before the node at `ply`, from the
+    /// point of view of the side to move at that node.
+    pub(crate) fn previous_static_eval(&self, ply: ScoreType, offset: ScoreType) -> Option<Score> {
+        self.at(ply - offset).and_then(|entry| entry.static_eval)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.fill(SearchStackEntry::default());
+    }
+}
+
+impl Default for SearchStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::{
+        moves::{Move, MoveDescriptor},
+        pieces::Piece,
+        square::Square,
+    };
+
+    use super::SearchStack;
+    use crate::{defs::MAX_DEPTH, score::Score};
+
+    fn knight_move(from: u8, to: u8) -> Move {
+        Move::new(
+            &Square::from_square_index(from),
+            &Square::from_square_index(to),
+            MoveDescriptor::None,
+            Piece::Knight,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn previous_moves() {
+        let mut stack = SearchStack::new();
+        let first = Move::new(
+            &Square::from_square_index(12),
+            &Square::from_square_index(28),
+            MoveDescriptor::PawnTwoUp,
+            Piece::Pawn,
+            None,
+            None,
+        );
+        let second = knight_move(57, 42);
+
+        stack.at_mut(0).unwrap().current_move = Some(first);
+        stack.at_mut(1).unwrap().current_move = Some(second);
+
+        assert_eq!(stack.previous_move(2, 1), Some(second));
+        assert_eq!(stack.previous_move(2, 2), Some(first));
+        // nothing before the root
+        assert_eq!(stack.previous_move(1, 2), None);
+        assert!(stack.at(MAX_DEPTH as i16).is_none());
+
+        stack.clear();
+        assert_eq!(stack.previous_move(2, 1), None);
+    }
+
+    #[test]
+    fn previous_static_evals() {
+        let mut stack = SearchStack::new();
+        stack.at_mut(0).unwrap().static_eval = Some(Score::new(25));
+        stack.at_mut(1).unwrap().static_eval = None;
+
+        assert_eq!(stack.previous_static_eval(2, 2), Some(Score::new(25)));
+        // no static eval when in check
+        assert_eq!(stack.previous_static_eval(2, 1), None);
+        // nothing before the root
+        assert_eq!(stack.previous_static_eval(1, 2), None);
+    }
+
+    #[test]
+    fn killers_are_stored_per_ply() {
+        let mut stack = SearchStack::new();
+        let killer = knight_move(1, 18);
+        stack.at_mut(3).unwrap().killers.update(killer);
+        assert_eq!(
+            stack.at(3).unwrap().killers.as_slice(),
+            &[Some(killer), None]
+        );
+
+        // other plies are not affected
+        assert_eq!(stack.at(2).unwrap().killers.as_slice(), &[None, None]);
+
+        stack.clear();
+        assert_eq!(stack.at(3).unwrap().killers.as_slice(), &[None, None]);
+    }
+}
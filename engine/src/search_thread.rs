@@ -14,16 +14,40 @@ use std::{
     thread::JoinHandle,
 };
 
-use chess::{board::Board, moves::Move, pieces::SQUARE_NAME};
-use uci_parser::{UciMove, UciResponse};
+use chess::{
+    board::Board, move_generation::MoveGenerator, move_list::MoveList, moves::Move,
+    pieces::SQUARE_NAME,
+};
+use uci_parser::{UciInfo, UciMove, UciResponse};
 
 use crate::{
+    correction_history::CorrectionHistory,
     history_table::HistoryTable,
-    log_level::{LogDebug, LogInfo, LogLevel},
-    search::{Search, SearchParameters},
+    log_level::{LogDebug, LogInfo, LogLevel, LogNone},
+    search::{Search, SearchParameters, SearchResult},
     ttable::TranspositionTable,
 };
 
+/// The maximum number of search threads that can be requested with the `Threads` option.
+pub const MAX_THREADS: usize = 256;
+
+/// Stack size of every search thread.
+const SEARCH_THREAD_STACK_SIZE: usize = 8 * 1024 * 1024; // 8 MiB
+
+/// History kept by a search thread between searches, cleared on `ucinewgame`.
+#[derive(Default)]
+pub(crate) struct ThreadHistory {
+    pub(crate) history_table: HistoryTable,
+    pub(crate) correction_history: CorrectionHistory,
+}
+
+impl ThreadHistory {
+    pub(crate) fn clear(&mut self) {
+        self.history_table.clear();
+        self.correction_history.clear();
+    }
+}
+
 fn square_index_to_uci_square(square: u8) -> uci_parser::Square {
     uci_parser::Square::from_str(SQUARE_NAME[square as usize]).unwrap()
 }
@@ -45,13 +69,116 @@ fn move_to_uci_move(mv: &Move) -> UciMove {
     }
 }
 
+/// Returns the reply we expect from our opponent, so that we can ponder on it. This is the second
+/// move of the PV, or the transposition table move after our best move when the PV is too short.
+fn ponder_move(
+    board: &mut Board,
+    result: &SearchResult,
+    ttable: &TranspositionTable,
+    move_gen: &MoveGenerator,
+) -> Option<Move> {
+    let best_move = result.best_move?;
+    let mut pv = result.pv.iter();
+    if pv.next() == Some(&best_move)
+        && let Some(reply) = pv.next()
+    {
+        return Some(*reply);
+    }
+
+    board.make_move_unchecked(&best_move).ok()?;
+    let mut move_list = MoveList::new();
+    move_gen.generate_legal_moves(board, &mut move_list);
+    let reply = ttable
+        .get_entry(board.zobrist_hash())
+        .and_then(|entry| entry.board_move.resolve(move_list.iter()));
+    board.unmake_move().ok()?;
+    reply
+}
+
+/// Runs a [Lazy SMP](https://www.chessprogramming.org/Lazy_SMP) search. One helper thread is
+/// started for every thread history after the first; all threads share the transposition table
+/// and only communicate through it. The calling thread runs the main search and its result is
+/// the one that gets reported, with the nodes of all threads. Once the main search finishes the
+/// helpers are stopped.
+fn lazy_smp_search(
+    board: &mut Board,
+    params: &SearchParameters,
+    ttable: &TranspositionTable,
+    histories: &mut [ThreadHistory],
+    stop_flag: Arc<AtomicBool>,
+    is_debug: bool,
+) -> SearchResult {
+    let Some((main_history, helper_histories)) = histories.split_first_mut() else {
+        return SearchResult::default();
+    };
+
+    // helpers get their own stop flag so that they can be stopped as soon as the main thread is done
+    let helper_stop_flag = Arc::new(AtomicBool::new(false));
+
+    std::thread::scope(|scope| {
+        let helpers = helper_histories
+            .iter_mut()
+            .enumerate()
+            .map(|(i, helper_history)| {
+                let thread_id = i + 1;
+                let helper_params = params.for_helper(thread_id);
+                let mut helper_board = board.clone();
+                let helper_flag = helper_stop_flag.clone();
+                std::thread::Builder::new()
+                    .name(format!("bk-helper-thread-{thread_id}"))
+                    .stack_size(SEARCH_THREAD_STACK_SIZE)
+                    .spawn_scoped(scope, move || {
+                        Search::<LogNone>::new(
+                            &helper_params,
+                            ttable,
+                            &mut helper_history.history_table,
+                            &mut helper_history.correction_history,
+                        )
+                        .search(&mut helper_board, Some(helper_flag))
+                        .nodes
+                    })
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let history_table = &mut main_history.history_table;
+        let correction_history = &mut main_history.correction_history;
+        let mut result = if is_debug {
+            Search::<LogDebug>::new(params, ttable, history_table, correction_history)
+                .search(board, Some(stop_flag))
+        } else {
+            Search::<LogInfo>::new(params, ttable, history_table, correction_history)
+                .search(board, Some(stop_flag))
+        };
+
+        helper_stop_flag.store(true, Ordering::Relaxed);
+        let helper_nodes = helpers
+            .into_iter()
+            .map(|helper| helper.join().unwrap())
+            .sum::<u64>();
+
+        // the info lines of the main search only counted its own nodes
+        if helper_nodes > 0 {
+            result.nodes += helper_nodes;
+            let elapsed = params.start_time.elapsed();
+            let info = UciInfo::new()
+                .nodes(result.nodes)
+                .nps((result.nodes as f32 / elapsed.as_secs_f32()).trunc())
+                .time(elapsed.as_millis() as u64);
+            println!("{}", UciResponse::info(info));
+        }
+
+        result
+    })
+}
+
 #[allow(clippy::large_enum_variant)]
 pub(crate) enum SearchThreadValue {
     Params(
         Board,
         SearchParameters,
-        Arc<Mutex<TranspositionTable>>,
-        Arc<Mutex<HistoryTable>>,
+        Arc<TranspositionTable>,
+        Arc<Mutex<Vec<ThreadHistory>>>,
         bool,
     ),
     Exit,
@@ -79,37 +206,34 @@ impl SearchThread {
 
         let handle = std::thread::Builder::new()
             .name("bk-search-thread".to_string())
-            .stack_size(8 * 1024 * 1024) // 8 MiB
+            .stack_size(SEARCH_THREAD_STACK_SIZE)
             .spawn(move || {
                 let mut stdout = std::io::stdout();
+                let move_gen = MoveGenerator::new();
                 'search_loop: loop {
                     let value = receiver.recv().unwrap();
                     match value {
                         SearchThreadValue::Params(mut board, params, ttable, history, is_debug) => {
-                            let mut tt = ttable.lock().unwrap();
-                            let mut hist_table = history.lock().unwrap();
+                            let mut histories = history.lock().unwrap();
                             let flag = stop_flag.clone();
                             is_searching.store(true, Ordering::Relaxed);
-                            let result = if is_debug {
-                                Search::<LogDebug>::new(&params, &mut tt, &mut hist_table)
-                                    .search(&mut board, Some(flag))
-                            } else {
-                                Search::<LogInfo>::new(&params, &mut tt, &mut hist_table)
-                                    .search(&mut board, Some(flag))
-                            };
+                            let result = lazy_smp_search(
+                                &mut board,
+                                &params,
+                                &ttable,
+                                histories.as_mut_slice(),
+                                flag,
+                                is_debug,
+                            );
                             is_searching.store(false, Ordering::Relaxed);
                             let best_move = result.best_move;
+                            let ponder_move = ponder_move(&mut board, &result, &ttable, &move_gen);
                             let move_output = UciResponse::BestMove {
                                 bestmove: best_move
                                     .map(|bot_move| move_to_uci_move(&bot_move).to_string()),
-                                ponder: None,
+                                ponder: ponder_move.map(|mv| move_to_uci_move(&mv).to_string()),
                             };
-                            writeln!(
-                                stdout,
-                                "{move_output}",
-                                // TODO: Ponder
-                            )
-                            .unwrap();
+                            writeln!(stdout, "{move_output}").unwrap();
                         }
 
                         SearchThreadValue::Exit => {
@@ -138,6 +262,15 @@ impl SearchThread {
     /// Stops the current search if any is in progress.
     pub(crate) fn stop_search(&self) {
         self.stop_search_flag.store(true, Ordering::Relaxed);
+        self.wake();
+    }
+
+    /// Wakes up a ponder or infinite search that is done and waiting for `ponderhit` or `stop`,
+    /// so it can check its flags again.
+    pub(crate) fn wake(&self) {
+        if let Some(handle) = &self.handle {
+            handle.thread().unpark();
+        }
     }
 
     /// Starts a new search with the given parameters and board state.
@@ -145,8 +278,8 @@ impl SearchThread {
         &self,
         board: &Board,
         params: SearchParameters,
-        ttable: Arc<Mutex<TranspositionTable>>,
-        history_table: Arc<Mutex<HistoryTable>>,
+        ttable: Arc<TranspositionTable>,
+        histories: Arc<Mutex<Vec<ThreadHistory>>>,
     ) {
         self.stop_search_flag.store(false, Ordering::Relaxed);
         self.sender
@@ -154,7 +287,7 @@ impl SearchThread {
                 board.clone(),
                 params,
                 ttable,
-                history_table,
+                histories,
                 Log::DEBUG,
             ))
             .unwrap();
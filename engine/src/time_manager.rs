@@ -0,0 +1,258 @@
+// Part of the byte-knight project.
+// Author: Paul Tsouchlos (ptsouchlos) (developer.paul.123@gmail.com)
+// GNU General Public License v3.0 or later
+// https://www.gnu.org/licenses/gpl-3.0-standalone.html
+
+use std::time::Duration;
+
+use chess::{moves::Move, side::Side};
+use uci_parser::UciSearchOptions;
+
+use crate::{
+    score::{LargeScoreType, Score},
+    tuneable::{
+        TM_DEFAULT_MOVES_TO_GO, TM_HARD_LIMIT_BASE, TM_HARD_LIMIT_PER_MOVE, TM_INCREMENT_FRACTION,
+        TM_MAX_HARD_LIMIT_SCALE, TM_MAX_MOVES_TO_GO, TM_MAX_SCORE_DROP, TM_MAX_TIME_FRACTION,
+        TM_NODE_FRACTION_BASE, TM_NODE_FRACTION_SCALE, TM_SCORE_DROP_SCALE, TM_STABILITY_SCALES,
+    },
+};
+
+/// Time kept back for every move by default, in milliseconds, see the `Move Overhead` option.
+pub const DEFAULT_MOVE_OVERHEAD_MS: u64 = 10;
+/// Largest move overhead that can be set with the `Move Overhead` option, in milliseconds.
+pub const MAX_MOVE_OVERHEAD_MS: u64 = 5000;
+
+/// Returns the soft and hard time limits for a search with the given UCI options, or `None` if
+/// the search isn't limited by time. `move_overhead` is kept back from the clock to cover the
+/// time lost communicating with the GUI.
+///
+/// Without `movestogo` the remaining time is split as if [`TM_DEFAULT_MOVES_TO_GO`] moves were
+/// left (sudden death). With `movestogo` it is split over the moves until the next time control.
+pub(crate) fn time_limits(
+    uci_options: &UciSearchOptions,
+    side: Side,
+    move_overhead: Duration,
+) -> Option<(Duration, Duration)> {
+    if let Some(time) = uci_options.movetime {
+        let time = time.saturating_sub(move_overhead);
+        return Some((time, time));
+    }
+
+    let (time, increment) = if side.is_white() {
+        (uci_options.wtime, uci_options.winc)
+    } else {
+        (uci_options.btime, uci_options.binc)
+    };
+    let time = time?.saturating_sub(move_overhead);
+    let increment = increment.unwrap_or(Duration::ZERO);
+    let moves_to_go = uci_options
+        .movestogo
+        .map_or(TM_DEFAULT_MOVES_TO_GO, |moves| {
+            moves.clamp(1, TM_MAX_MOVES_TO_GO)
+        });
+
+    let optimum = time / moves_to_go + increment.mul_f64(TM_INCREMENT_FRACTION);
+    // with few moves left until the next time control, a long search can't be made up for later
+    let hard_scale = (TM_HARD_LIMIT_BASE + f64::from(moves_to_go) * TM_HARD_LIMIT_PER_MOVE)
+        .min(TM_MAX_HARD_LIMIT_SCALE);
+    let hard = optimum
+        .mul_f64(hard_scale)
+        .min(time.mul_f64(TM_MAX_TIME_FRACTION));
+    Some((optimum.min(hard), hard))
+}
+
+/// Adjusts the soft time limit of a search after every iteration of iterative deepening.
+/// See <https://www.chessprogramming.org/Time_Management>
+///
+/// The search gets more time when the best move keeps changing, when the score drops and when
+/// the other root moves needed a lot of nodes to refute, and less time when the best move is
+/// clear. The soft limit never goes past the hard limit.
+pub(crate) struct TimeManager {
+    /// Soft limit before any scaling.
+    optimum: Duration,
+    soft_limit: Duration,
+    hard_limit: Duration,
+    best_move: Option<Move>,
+    /// Number of iterations in a row that ended with `best_move`.
+    stability: usize,
+    previous_score: Option<Score>,
+}
+
+impl TimeManager {
+    pub(crate) fn new(soft_limit: Duration, hard_limit: Duration) -> Self {
+        Self {
+            optimum: soft_limit,
+            soft_limit: soft_limit.min(hard_limit),
+            hard_limit,
+            best_move: None,
+            stability: 0,
+            previous_score: None,
+        }
+    }
+
+    /// The search should not start a new iteration once this much time has passed.
+    pub(crate) fn soft_limit(&self) -> Duration {
+        self.soft_limit
+    }
+
+    /// Updates the soft limit after an iteration that found `best_move` with `score`. Of the
+    /// `total_nodes` nodes searched so far, `best_move_nodes` were spent on the best move.
+    pub(crate) fn update(
+        &mut self,
+        best_move: Move,
+        score: Score,
+        best_move_nodes: u64,
+        total_nodes: u64,
+    ) {
+        if self.best_move == Some(best_move) {
+            self.stability = (self.stability + 1).min(TM_STABILITY_SCALES.len() - 1);
+        } else {
+            self.best_move = Some(best_move);
+            self.stability = 0;
+        }
+        let stability_scale = TM_STABILITY_SCALES[self.stability];
+
+        let score_drop = self.previous_score.map_or(0, |previous| {
+            LargeScoreType::from(previous.0) - LargeScoreType::from(score.0)
+        });
+        let score_scale = 1.0 + score_drop.clamp(0, TM_MAX_SCORE_DROP) as f64 * TM_SCORE_DROP_SCALE;
+        self.previous_score = Some(score);
+
+        let node_fraction = best_move_nodes as f64 / total_nodes.max(1) as f64;
+        let node_scale = (TM_NODE_FRACTION_BASE - node_fraction) * TM_NODE_FRACTION_SCALE;
+
+        let scale = stability_scale * score_scale * node_scale;
+        self.soft_limit = Duration::try_from_secs_f64(self.optimum.as_secs_f64() * scale)
+            .unwrap_or(Duration::MAX)
+            .min(self.hard_limit);
+    }
+}
+
+impl Default for TimeManager {
+    fn default() -> Self {
+        Self::new(Duration::MAX, Duration::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chess::{board::Board, side::Side};
+    use uci_parser::UciSearchOptions;
+
+    use super::{TimeManager, time_limits};
+    use crate::{
+        score::{Score, ScoreType},
+        tuneable::{TM_DEFAULT_MOVES_TO_GO, TM_MAX_TIME_FRACTION},
+    };
+
+    fn clock(time_ms: u64, increment_ms: u64, moves_to_go: Option<u32>) -> UciSearchOptions {
+        UciSearchOptions {
+            wtime: Some(Duration::from_millis(time_ms)),
+            winc: Some(Duration::from_millis(increment_ms)),
+            movestogo: moves_to_go,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn searches_without_a_clock_have_no_limits() {
+        let options = UciSearchOptions::default();
+        assert!(time_limits(&options, Side::White, Duration::ZERO).is_none());
+        // black has no time on the clock here
+        assert!(time_limits(&clock(60_000, 0, None), Side::Black, Duration::ZERO).is_none());
+    }
+
+    #[test]
+    fn move_time_keeps_back_the_overhead() {
+        let options = UciSearchOptions {
+            movetime: Some(Duration::from_millis(1000)),
+            ..Default::default()
+        };
+        let expected = Duration::from_millis(900);
+        assert_eq!(
+            time_limits(&options, Side::White, Duration::from_millis(100)),
+            Some((expected, expected))
+        );
+    }
+
+    #[test]
+    fn sudden_death_limits() {
+        let time = Duration::from_millis(60_000);
+        let (soft, hard) =
+            time_limits(&clock(60_000, 0, None), Side::White, Duration::ZERO).unwrap();
+        assert_eq!(soft, time / TM_DEFAULT_MOVES_TO_GO);
+        assert!(soft < hard && hard <= time.mul_f64(TM_MAX_TIME_FRACTION));
+
+        // the increment adds time, the overhead takes it away
+        let (with_increment, _) =
+            time_limits(&clock(60_000, 1000, None), Side::White, Duration::ZERO).unwrap();
+        assert!(with_increment > soft);
+        let (with_overhead, _) = time_limits(
+            &clock(60_000, 0, None),
+            Side::White,
+            Duration::from_millis(1000),
+        )
+        .unwrap();
+        assert!(with_overhead < soft);
+    }
+
+    #[test]
+    fn moves_to_go_limits() {
+        let time = Duration::from_millis(60_000);
+        let (sudden_death, _) =
+            time_limits(&clock(60_000, 0, None), Side::White, Duration::ZERO).unwrap();
+        let (ten_moves, _) =
+            time_limits(&clock(60_000, 0, Some(10)), Side::White, Duration::ZERO).unwrap();
+        assert!(ten_moves > sudden_death);
+
+        // the last move before the time control still can't use all of the time
+        let (soft, hard) =
+            time_limits(&clock(60_000, 0, Some(1)), Side::White, Duration::ZERO).unwrap();
+        assert!(soft <= hard && hard <= time.mul_f64(TM_MAX_TIME_FRACTION));
+    }
+
+    #[test]
+    fn soft_limit_scales_with_the_search() {
+        let optimum = Duration::from_millis(1000);
+        let hard = Duration::from_millis(5000);
+        let board = Board::default_board();
+        let mut moves = chess::move_list::MoveList::new();
+        chess::move_generation::MoveGenerator::new().generate_legal_moves(&board, &mut moves);
+        let best = *moves.at(0).unwrap();
+        let other = *moves.at(1).unwrap();
+
+        // a best move that stays the same and takes most of the nodes needs less time
+        let mut stable = TimeManager::new(optimum, hard);
+        for i in 1..=5 {
+            stable.update(best, Score::new(50), i * 900, i * 1000);
+        }
+        assert!(stable.soft_limit() < optimum);
+
+        // a best move that keeps changing needs more time
+        let mut unstable = TimeManager::new(optimum, hard);
+        for i in 0..5 {
+            let mv = if i % 2 == 0 { best } else { other };
+            unstable.update(mv, Score::new(50), 500, 1000);
+        }
+        assert!(unstable.soft_limit() > stable.soft_limit());
+
+        // so does a dropping score
+        let mut dropping = TimeManager::new(optimum, hard);
+        for i in 1..=5 {
+            dropping.update(
+                best,
+                Score::new(90 - 40 * i as ScoreType),
+                i * 900,
+                i * 1000,
+            );
+        }
+        assert!(dropping.soft_limit() > stable.soft_limit());
+
+        // but never more than the hard limit
+        let mut capped = TimeManager::new(optimum, Duration::from_millis(1500));
+        capped.update(best, Score::new(50), 500, 1000);
+        assert_eq!(capped.soft_limit(), Duration::from_millis(1500));
+    }
+}
@@ -3,12 +3,34 @@
 // GNU General Public License v3.0 or later
 // https://www.gnu.org/licenses/gpl-3.0-standalone.html
 
+use std::sync::atomic::{AtomicU8, AtomicU64, AtomicUsize, Ordering};
+
 use chess::moves::Move;
 
-use crate::{node_types::NodeType, score::Score};
+use crate::{
+    node_types::NodeType,
+    score::{Score, ScoreType},
+};
 
 const BYTES_PER_MB: usize = 1024 * 1024;
 
+/// Number of entries sampled when calculating the UCI `hashfull` value.
+const HASHFULL_SAMPLE_SIZE: usize = 1000;
+
+/// Number of entries stored in a single [`Cluster`].
+const CLUSTER_SIZE: usize = 4;
+
+/// Number of distinct generations before the counter wraps around. Matches the number of bits used
+/// to store the age of an entry.
+const GENERATION_CYCLE: u8 = 64;
+
+/// How many plies of depth a single generation of age is worth when picking an entry to replace.
+const AGE_REPLACEMENT_WEIGHT: i32 = 8;
+
+/// An entry for the same position is only overwritten with a shallower result from the same
+/// search if it is within this many plies of the stored depth.
+const SAME_POSITION_DEPTH_MARGIN: u8 = 4;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum EntryFlag {
     Exact,
@@ -16,16 +38,59 @@ pub enum EntryFlag {
     UpperBound,
 }
 
+/// A compact 16-bit version of a [`Move`] that only stores the from/to squares and the promotion.
+///
+/// The full move is recovered by matching it against the moves generated for the position, see
+/// [`PackedMove::resolve`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct PackedMove(u16);
+
+impl PackedMove {
+    pub(crate) fn new(mv: &Move) -> Self {
+        let promotion = if mv.is_promotion() {
+            mv.promotion_description() as u16 + 1
+        } else {
+            0
+        };
+        Self(mv.from() as u16 | ((mv.to() as u16) << 6) | (promotion << 12))
+    }
+
+    pub(crate) fn matches(&self, mv: &Move) -> bool {
+        *self == Self::new(mv)
+    }
+
+    /// Finds the full move in the given list of moves, if present.
+    pub(crate) fn resolve<'m>(&self, moves: impl IntoIterator<Item = &'m Move>) -> Option<Move> {
+        moves.into_iter().find(|mv| self.matches(mv)).copied()
+    }
+}
+
 /// A transposition table entry.
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct TranspositionTableEntry {
     pub zobrist: u64,
     pub score: Score,
-    pub board_move: Move,
+    pub board_move: PackedMove,
     pub depth: u8,
     pub flag: EntryFlag,
+    pub age: u8,
 }
 
+// Layout of a packed entry, from LSB to MSB:
+// 16 bits for the key, 16 bits for the move, 16 bits for the score, 8 bits for the depth,
+// 2 bits for the flag and 6 bits for the age.
+const KEY_MASK: u64 = 0xFFFF;
+const MOVE_SHIFT: u32 = 16;
+const MOVE_MASK: u64 = 0xFFFF;
+const SCORE_SHIFT: u32 = 32;
+const SCORE_MASK: u64 = 0xFFFF;
+const DEPTH_SHIFT: u32 = 48;
+const DEPTH_MASK: u64 = 0xFF;
+const FLAG_SHIFT: u32 = 56;
+const FLAG_MASK: u64 = 0b11;
+const AGE_SHIFT: u32 = 58;
+const AGE_MASK: u64 = 0b11_1111;
+
 impl TranspositionTableEntry {
     #[allow(dead_code)]
     pub fn new(
@@ -40,17 +105,100 @@ impl TranspositionTableEntry {
             depth,
             score,
             flag,
-            board_move: mv,
+            board_move: PackedMove::new(&mv),
+            age: 0,
         }
     }
+
+    /// The part of the zobrist hash that is stored in the table to verify entries.
+    const fn key(zobrist: u64) -> u16 {
+        (zobrist & KEY_MASK) as u16
+    }
+
+    /// Packs the entry into a single 64-bit value.
+    /// The flag is stored as 1..=3 so a packed entry is never zero.
+    fn pack(&self) -> u64 {
+        let flag = match self.flag {
+            EntryFlag::Exact => 1,
+            EntryFlag::LowerBound => 2,
+            EntryFlag::UpperBound => 3,
+        };
+
+        (Self::key(self.zobrist) as u64)
+            | ((self.board_move.0 as u64) << MOVE_SHIFT)
+            | ((self.score.0 as u16 as u64) << SCORE_SHIFT)
+            | ((self.depth as u64) << DEPTH_SHIFT)
+            | (flag << FLAG_SHIFT)
+            | (((self.age as u64) & AGE_MASK) << AGE_SHIFT)
+    }
+
+    /// Unpacks an entry that was packed with [`TranspositionTableEntry::pack`].
+    /// Returns `None` for empty slots. The key is not verified, see [`Cluster::find`].
+    fn unpack(zobrist: u64, data: u64) -> Option<TranspositionTableEntry> {
+        let flag = match (data >> FLAG_SHIFT) & FLAG_MASK {
+            1 => EntryFlag::Exact,
+            2 => EntryFlag::LowerBound,
+            3 => EntryFlag::UpperBound,
+            _ => return None,
+        };
+
+        Some(TranspositionTableEntry {
+            zobrist,
+            score: Score::new(((data >> SCORE_SHIFT) & SCORE_MASK) as u16 as i16),
+            board_move: PackedMove(((data >> MOVE_SHIFT) & MOVE_MASK) as u16),
+            depth: ((data >> DEPTH_SHIFT) & DEPTH_MASK) as u8,
+            flag,
+            age: ((data >> AGE_SHIFT) & AGE_MASK) as u8,
+        })
+    }
+}
+
+/// A group of entries that share a single index in the [`TranspositionTable`] and fit in half a
+/// cache line.
+///
+/// Every entry is a single 64-bit word, so reads and writes from different threads can never be
+/// torn. Entries are verified with the lower 16 bits of the zobrist hash, the upper bits are
+/// implied by the index of the cluster.
+#[derive(Debug, Default)]
+#[repr(align(32))]
+struct Cluster {
+    entries: [AtomicU64; CLUSTER_SIZE],
+}
+
+impl Cluster {
+    fn find(&self, zobrist: u64) -> Option<TranspositionTableEntry> {
+        let key = TranspositionTableEntry::key(zobrist) as u64;
+        self.entries
+            .iter()
+            .map(|entry| entry.load(Ordering::Relaxed))
+            .find(|&data| data != 0 && data & KEY_MASK == key)
+            .and_then(|data| TranspositionTableEntry::unpack(zobrist, data))
+    }
+
+    fn clear(&self) {
+        self.entries
+            .iter()
+            .for_each(|entry| entry.store(0, Ordering::Relaxed));
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|entry| entry.load(Ordering::Relaxed) == 0)
+    }
 }
 
 /// A transposition table used to store the results of previous searches.
+///
+/// The table can be probed and written to by several search threads at once without locking.
+/// Entries are grouped into [`Cluster`]s and replaced based on their depth and how many searches
+/// ago they were written.
 pub struct TranspositionTable {
-    table: Vec<Option<TranspositionTableEntry>>,
-    pub(crate) collisions: usize,
-    pub(crate) accesses: usize,
-    pub(crate) hits: usize,
+    table: Vec<Cluster>,
+    generation: AtomicU8,
+    collisions: AtomicUsize,
+    accesses: AtomicUsize,
+    hits: AtomicUsize,
 }
 
 pub const MAX_TABLE_SIZE_MB: usize = 1024;
@@ -78,52 +226,148 @@ pub(crate) enum ProbeResult {
 }
 
 impl TranspositionTable {
+    /// Creates a table that holds at least `capacity` entries.
     pub(crate) fn from_capacity(capacity: usize) -> Self {
+        let clusters = capacity.div_ceil(CLUSTER_SIZE).max(1);
         Self {
-            table: vec![None; capacity],
-            collisions: 0,
-            accesses: 0,
-            hits: 0,
+            table: (0..clusters).map(|_| Cluster::default()).collect(),
+            generation: AtomicU8::new(0),
+            collisions: AtomicUsize::new(0),
+            accesses: AtomicUsize::new(0),
+            hits: AtomicUsize::new(0),
         }
     }
 
     pub(crate) fn from_size_in_mb(mb: usize) -> Self {
-        let capacity = mb * BYTES_PER_MB / std::mem::size_of::<TranspositionTableEntry>();
-        Self::from_capacity(capacity)
+        let clusters = mb * BYTES_PER_MB / std::mem::size_of::<Cluster>();
+        Self::from_capacity(clusters * CLUSTER_SIZE)
     }
 
+    /// Returns the index of the cluster for the given hash.
     fn get_index(&self, zobrist: u64) -> usize {
         fast_range_64(zobrist, self.table.len() as u64) as usize
     }
 
     pub(crate) fn get_entry(&self, zobrist: u64) -> Option<TranspositionTableEntry> {
-        let index = self.get_index(zobrist);
-        self.table[index]
+        self.table[self.get_index(zobrist)].find(zobrist)
     }
 
-    pub(crate) fn store_entry(&mut self, entry: TranspositionTableEntry) {
-        let index = self.get_index(entry.zobrist);
-        self.table[index] = Some(entry);
+    /// Stores an entry in its cluster, tagged with the current generation.
+    ///
+    /// An existing entry for the same position is updated in place. Otherwise an empty slot is
+    /// used if there is one, or the entry with the lowest depth, discounted by its age, is replaced.
+    pub(crate) fn store_entry(&self, mut entry: TranspositionTableEntry) {
+        let generation = self.generation();
+        entry.age = generation;
+
+        let cluster = &self.table[self.get_index(entry.zobrist)];
+        let key = TranspositionTableEntry::key(entry.zobrist) as u64;
+
+        let mut replace_index = 0;
+        let mut replace_value = i32::MAX;
+        for (index, slot) in cluster.entries.iter().enumerate() {
+            let data = slot.load(Ordering::Relaxed);
+            let Some(existing) = TranspositionTableEntry::unpack(entry.zobrist, data) else {
+                // empty slot, use it unless we find the same position later in the cluster
+                if replace_value != i32::MIN {
+                    replace_index = index;
+                    replace_value = i32::MIN;
+                }
+                continue;
+            };
+
+            if data & KEY_MASK == key {
+                // keep deeper results for the same position from this search
+                if entry.flag != EntryFlag::Exact
+                    && existing.age == generation
+                    && entry.depth.saturating_add(SAME_POSITION_DEPTH_MARGIN) < existing.depth
+                {
+                    return;
+                }
+                replace_index = index;
+                break;
+            }
+
+            let value = existing.depth as i32
+                - AGE_REPLACEMENT_WEIGHT * self.relative_age(existing.age) as i32;
+            if value < replace_value {
+                replace_index = index;
+                replace_value = value;
+            }
+        }
+
+        cluster.entries[replace_index].store(entry.pack(), Ordering::Relaxed);
     }
 
-    pub(crate) fn clear(&mut self) {
-        self.table.iter_mut().for_each(|element| {
-            *element = None;
-        });
+    /// Returns the current generation of the table.
+    pub(crate) fn generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Starts a new generation. Should be called at the start of each search so that entries from
+    /// previous searches are replaced first.
+    pub(crate) fn new_search(&self) {
+        let next = (self.generation() + 1) % GENERATION_CYCLE;
+        self.generation.store(next, Ordering::Relaxed);
+    }
+
+    /// Returns how many generations ago an entry with the given age was written.
+    fn relative_age(&self, age: u8) -> u8 {
+        self.generation().wrapping_sub(age) % GENERATION_CYCLE
+    }
+
+    pub(crate) fn clear(&self) {
+        self.table.iter().for_each(|cluster| cluster.clear());
 
         // reset stats as well
-        self.collisions = 0;
-        self.accesses = 0;
-        self.hits = 0;
+        self.collisions.store(0, Ordering::Relaxed);
+        self.accesses.store(0, Ordering::Relaxed);
+        self.hits.store(0, Ordering::Relaxed);
+    }
+
+    fn entries(&self) -> impl Iterator<Item = u64> + '_ {
+        self.table.iter().flat_map(|cluster| {
+            cluster
+                .entries
+                .iter()
+                .map(|entry| entry.load(Ordering::Relaxed))
+        })
     }
 
     pub(crate) fn fullness(&self) -> f64 {
-        (self.table.iter().filter(|entry| entry.is_some()).count() as f64 / self.table.len() as f64)
-            * 100_f64
+        (self.entries().filter(|&data| data != 0).count() as f64 / self.size() as f64) * 100_f64
+    }
+
+    /// Returns how full the table is in permill, as expected by the UCI `hashfull` info field.
+    /// Only entries written during the current search are counted, and only the first
+    /// [`HASHFULL_SAMPLE_SIZE`] entries are sampled so this is cheap to call.
+    pub(crate) fn hashfull(&self) -> usize {
+        let generation = self.generation();
+        let sample_size = HASHFULL_SAMPLE_SIZE.min(self.size());
+        let used = self
+            .entries()
+            .take(sample_size)
+            .filter_map(|data| TranspositionTableEntry::unpack(0, data))
+            .filter(|entry| entry.age == generation)
+            .count();
+        used * 1000 / sample_size.max(1)
     }
 
+    /// Returns the number of entries the table can hold.
     pub(crate) fn size(&self) -> usize {
-        self.table.len()
+        self.table.len() * CLUSTER_SIZE
+    }
+
+    pub(crate) fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn accesses(&self) -> usize {
+        self.accesses.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn collisions(&self) -> usize {
+        self.collisions.load(Ordering::Relaxed)
     }
 
     /// Probes the transposition table for a potential entry/cutoff.
@@ -131,77 +375,95 @@ impl TranspositionTable {
     /// # Arguments
     ///
     /// - `depth` - The depth of the search.
+    /// - `ply` - The distance from the root, used to adjust mate scores.
     /// - `zobrist` - The zobrist hash of the position.
     /// - `alpha` - The alpha value of the search.
     /// - `beta` - The beta value of the search.
     ///
     /// # Returns
     ///
-    /// - `ProbeResult` - The result of the probe.
+    /// - `ProbeResult` - The result of the probe. Scores are relative to the root.
     pub(crate) fn probe<Node: NodeType>(
-        &mut self,
+        &self,
         depth: i16,
+        ply: ScoreType,
         zobrist: u64,
         alpha: Score,
         beta: Score,
     ) -> ProbeResult {
-        if let Some(entry) = self.get_entry(zobrist) {
-            self.accesses += 1;
-            // verify the zobrist hash as we could have collisions due to using modulo as a hash function
-            // and the fact that we are using a fixed size table.
-            if entry.zobrist == zobrist {
-                self.hits += 1;
-                if entry.depth >= depth as u8 {
-                    // can we cut off?
-                    // cutoff can only happen if the entry depth >= current depth and 1 of the following:
-                    // - the entry type is exact
-                    // - the entry type is lower bound and the score >= beta
-                    // - the entry type is upper bound and the score <= alpha
-                    // see https://www.chessprogramming.org/Transposition_Table#Transposition_Table_Cutoffs
-                    if entry.flag == EntryFlag::Exact
-                        || ((entry.flag == EntryFlag::LowerBound && entry.score >= beta)
-                            || (entry.flag == EntryFlag::UpperBound && entry.score <= alpha))
-                    {
-                        return ProbeResult::CutOff(entry);
-                    }
+        let cluster = &self.table[self.get_index(zobrist)];
+        if cluster.is_empty() {
+            return ProbeResult::Empty;
+        }
+
+        self.accesses.fetch_add(1, Ordering::Relaxed);
+        // verify the key as other positions can map to the same cluster
+        if let Some(mut entry) = cluster.find(zobrist) {
+            entry.score = entry.score.from_tt(ply);
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            if entry.depth >= depth as u8 {
+                // can we cut off?
+                // cutoff can only happen if the entry depth >= current depth and 1 of the following:
+                // - the entry type is exact
+                // - the entry type is lower bound and the score >= beta
+                // - the entry type is upper bound and the score <= alpha
+                // see https://www.chessprogramming.org/Transposition_Table#Transposition_Table_Cutoffs
+                if entry.flag == EntryFlag::Exact
+                    || ((entry.flag == EntryFlag::LowerBound && entry.score >= beta)
+                        || (entry.flag == EntryFlag::UpperBound && entry.score <= alpha))
+                {
+                    return ProbeResult::CutOff(entry);
                 }
-                return ProbeResult::Hit(entry);
-            } else {
-                // collision
-                self.collisions += 1;
             }
+            return ProbeResult::Hit(entry);
         }
 
+        // collision
+        self.collisions.fetch_add(1, Ordering::Relaxed);
         ProbeResult::Empty
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{EntryFlag, TranspositionTable, TranspositionTableEntry};
-    use crate::score::Score;
+    use super::{CLUSTER_SIZE, EntryFlag, PackedMove, TranspositionTable, TranspositionTableEntry};
+    use crate::{node_types::NonPvNode, score::Score};
     use chess::{
+        board::Board,
+        move_generation::MoveGenerator,
+        move_list::MoveList,
         moves::{Move, MoveDescriptor},
         pieces::Piece,
         square::Square,
     };
     use itertools::Itertools;
     use rand::Rng;
-    use std::collections::HashMap;
+    use std::{collections::HashMap, sync::atomic::Ordering};
+
+    fn knight_move() -> Move {
+        Move::new(
+            &Square::from_square_index(1),
+            &Square::from_square_index(18),
+            MoveDescriptor::None,
+            Piece::Knight,
+            None,
+            None,
+        )
+    }
 
     #[test]
     fn get_index() {
         let tt = TranspositionTable::from_size_in_mb(32);
         let mut rng = rand::rng();
-        let random_numbers: Vec<u64> = (0..tt.size()).map(|_| rng.random::<u64>()).collect();
+        let random_numbers: Vec<u64> = (0..tt.table.len()).map(|_| rng.random::<u64>()).collect();
         let min = random_numbers.iter().min().unwrap();
         let max = random_numbers.iter().max().unwrap();
         println!("min/max random number: {min}/{max}");
-        println!("Table size: {}", tt.size());
+        println!("Table size: {}", tt.table.len());
         let mut index_histogram: HashMap<usize, usize> = HashMap::new();
         random_numbers.iter().for_each(|&num| {
             let index = tt.get_index(num);
-            assert!(index < tt.size());
+            assert!(index < tt.table.len());
             *index_histogram.entry(index).or_insert(0) += 1;
         });
 
@@ -220,7 +482,7 @@ mod tests {
 
     #[test]
     fn store_and_retrieve() {
-        let mut tt = TranspositionTable::from_size_in_mb(16);
+        let tt = TranspositionTable::from_size_in_mb(16);
         let hash1 = 1234512341999_u64;
         let hash2 = 2423498723999_u64;
         let hash3 = 2423623733999_u64;
@@ -249,7 +511,6 @@ mod tests {
             None,
         );
 
-        // our tt implementation always overwrites, so let's make sure that's the case.
         tt.store_entry(TranspositionTableEntry::new(
             hash1,
             3,
@@ -260,7 +521,7 @@ mod tests {
 
         let stored_entry1 = tt.get_entry(hash1);
         assert!(stored_entry1.is_some());
-        assert_eq!(stored_entry1.unwrap().board_move, mv1);
+        assert!(stored_entry1.unwrap().board_move.matches(&mv1));
 
         tt.store_entry(TranspositionTableEntry::new(
             hash2,
@@ -272,7 +533,7 @@ mod tests {
 
         let stored_entry2 = tt.get_entry(hash2);
         assert!(stored_entry2.is_some());
-        assert_eq!(stored_entry2.unwrap().board_move, mv2);
+        assert!(stored_entry2.unwrap().board_move.matches(&mv2));
 
         tt.store_entry(TranspositionTableEntry::new(
             hash3,
@@ -284,6 +545,238 @@ mod tests {
 
         let stored_entry3 = tt.get_entry(hash3);
         assert!(stored_entry3.is_some());
-        assert_eq!(stored_entry3.unwrap().board_move, mv3);
+        assert!(stored_entry3.unwrap().board_move.matches(&mv3));
+
+        // the earlier entries are still there
+        assert!(tt.get_entry(hash1).is_some());
+        assert!(tt.get_entry(hash2).is_some());
+    }
+
+    #[test]
+    fn pack_and_unpack() {
+        for (score, flag, age) in [
+            (Score::new(-123), EntryFlag::Exact, 0),
+            (Score::MATE - 7, EntryFlag::LowerBound, 17),
+            (-Score::MATE + 3, EntryFlag::UpperBound, 63),
+        ] {
+            let mut entry =
+                TranspositionTableEntry::new(0xDEAD_BEEF, 42, score, flag, knight_move());
+            entry.age = age;
+            let unpacked = TranspositionTableEntry::unpack(entry.zobrist, entry.pack()).unwrap();
+            assert_eq!(unpacked.score, entry.score);
+            assert_eq!(unpacked.depth, entry.depth);
+            assert_eq!(unpacked.flag, entry.flag);
+            assert_eq!(unpacked.age, entry.age);
+            assert_eq!(unpacked.board_move, entry.board_move);
+        }
+
+        // empty slots never unpack
+        assert!(TranspositionTableEntry::unpack(0, 0).is_none());
+    }
+
+    #[test]
+    fn packed_moves_resolve_to_legal_moves() {
+        let board = Board::from_fen("4k3/1P6/8/8/8/8/8/4K1N1 w - - 0 1").unwrap();
+        let move_gen = MoveGenerator::new();
+        let mut move_list = MoveList::new();
+        move_gen.generate_legal_moves(&board, &mut move_list);
+
+        for mv in move_list.iter() {
+            let packed = PackedMove::new(mv);
+            assert_eq!(packed.resolve(move_list.iter()), Some(*mv));
+        }
+
+        // all four promotions have a distinct packed move
+        let promotions = move_list
+            .iter()
+            .filter(|mv| mv.is_promotion())
+            .map(PackedMove::new)
+            .unique()
+            .count();
+        assert_eq!(promotions, 4);
+
+        // moves that are not in the list do not resolve
+        let packed = PackedMove::new(&knight_move());
+        assert!(packed.resolve(move_list.iter()).is_none());
+    }
+
+    #[test]
+    fn mismatched_keys_are_rejected() {
+        let tt = TranspositionTable::from_capacity(CLUSTER_SIZE);
+        let hash = 0x1234_5678_9ABC_DEF0;
+        tt.store_entry(TranspositionTableEntry::new(
+            hash,
+            5,
+            Score::new(50),
+            EntryFlag::Exact,
+            knight_move(),
+        ));
+        assert!(matches!(
+            tt.probe::<NonPvNode>(5, 0, hash, -Score::INF, Score::INF),
+            super::ProbeResult::CutOff(_)
+        ));
+
+        // same cluster, different key
+        assert!(matches!(
+            tt.probe::<NonPvNode>(5, 0, hash ^ 1, -Score::INF, Score::INF),
+            super::ProbeResult::Empty
+        ));
+        assert_eq!(tt.collisions(), 1);
+    }
+
+    #[test]
+    fn probed_mate_scores_are_relative_to_the_root() {
+        let tt = TranspositionTable::from_capacity(CLUSTER_SIZE);
+        let hash = 0xABCD_EF01_2345_6789;
+
+        // mate in 5 plies from the root, stored at ply 2
+        let mate = Score::MATE - 5;
+        tt.store_entry(TranspositionTableEntry::new(
+            hash,
+            4,
+            mate.to_tt(2),
+            EntryFlag::Exact,
+            knight_move(),
+        ));
+
+        let probe_score = |ply| match tt.probe::<NonPvNode>(4, ply, hash, -Score::INF, Score::INF) {
+            super::ProbeResult::CutOff(entry) => Some(entry.score),
+            _ => None,
+        };
+        assert_eq!(probe_score(2), Some(mate));
+        // the same position reached at ply 6 is two moves further from the root
+        assert_eq!(probe_score(6), Some(mate - 4));
+    }
+
+    #[test]
+    fn replaces_shallowest_entry_in_cluster() {
+        let tt = TranspositionTable::from_capacity(CLUSTER_SIZE);
+        let store = |hash: u64, depth: u8| {
+            tt.store_entry(TranspositionTableEntry::new(
+                hash,
+                depth,
+                Score::new(0),
+                EntryFlag::LowerBound,
+                knight_move(),
+            ))
+        };
+
+        for (hash, depth) in [(1, 10), (2, 2), (3, 8), (4, 6)] {
+            store(hash, depth);
+        }
+        store(5, 5);
+        assert!(tt.get_entry(2).is_none());
+        for hash in [1, 3, 4, 5] {
+            assert!(tt.get_entry(hash).is_some());
+        }
+
+        // a much shallower result for the same position does not overwrite a deep one
+        store(1, 3);
+        assert_eq!(tt.get_entry(1).unwrap().depth, 10);
+        store(1, 7);
+        assert_eq!(tt.get_entry(1).unwrap().depth, 7);
+    }
+
+    #[test]
+    fn old_entries_are_replaced_first() {
+        let tt = TranspositionTable::from_capacity(CLUSTER_SIZE);
+        let store = |hash: u64, depth: u8| {
+            tt.store_entry(TranspositionTableEntry::new(
+                hash,
+                depth,
+                Score::new(0),
+                EntryFlag::Exact,
+                knight_move(),
+            ))
+        };
+
+        for (hash, depth) in [(1, 10), (2, 9), (3, 8), (4, 7)] {
+            store(hash, depth);
+        }
+
+        // entries from two searches ago are worth less than a fresh shallow entry
+        tt.new_search();
+        tt.new_search();
+        store(5, 1);
+        store(6, 1);
+        assert!(tt.get_entry(4).is_none());
+        assert!(tt.get_entry(3).is_none());
+        for hash in [1, 2, 5, 6] {
+            assert!(tt.get_entry(hash).is_some());
+        }
+        assert_eq!(tt.get_entry(5).unwrap().age, tt.generation());
+    }
+
+    #[test]
+    fn generation_wraps_around() {
+        let tt = TranspositionTable::from_capacity(CLUSTER_SIZE);
+        for _ in 0..super::GENERATION_CYCLE {
+            tt.new_search();
+        }
+        assert_eq!(tt.generation(), 0);
+        tt.new_search();
+        assert_eq!(tt.relative_age(super::GENERATION_CYCLE - 1), 2);
+    }
+
+    #[test]
+    fn concurrent_store_and_probe() {
+        let tt = TranspositionTable::from_size_in_mb(1);
+        let mv = knight_move();
+
+        std::thread::scope(|scope| {
+            for thread in 0..4u64 {
+                let tt = &tt;
+                scope.spawn(move || {
+                    let mut rng = rand::rng();
+                    for _ in 0..10_000 {
+                        let hash = rng.random::<u64>();
+                        let depth = (hash % 64) as u8;
+                        tt.store_entry(TranspositionTableEntry::new(
+                            hash,
+                            depth,
+                            Score::new(thread as i16),
+                            EntryFlag::Exact,
+                            mv,
+                        ));
+
+                        // anything we read back must be a complete entry written by one of the threads
+                        if let Some(entry) = tt.get_entry(hash) {
+                            assert!(entry.depth < 64);
+                            assert!((0..4).contains(&entry.score.0));
+                            assert!(entry.board_move.matches(&mv));
+                        }
+                    }
+                });
+            }
+        });
+
+        assert!(tt.fullness() > 0.0);
+    }
+
+    #[test]
+    fn hashfull_is_permill() {
+        let tt = TranspositionTable::from_capacity(2000);
+        assert_eq!(tt.hashfull(), 0);
+
+        for i in 0..500 {
+            let entry = TranspositionTableEntry::new(
+                i as u64 + 1,
+                1,
+                Score::new(0),
+                EntryFlag::Exact,
+                knight_move(),
+            );
+            tt.table[i / CLUSTER_SIZE].entries[i % CLUSTER_SIZE]
+                .store(entry.pack(), Ordering::Relaxed);
+        }
+        assert_eq!(tt.hashfull(), 500);
+
+        // entries from previous searches are not counted
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+        assert!(tt.fullness() > 0.0);
+
+        tt.clear();
+        assert_eq!(tt.fullness(), 0.0);
     }
 }
@@ -3,7 +3,7 @@
 // GNU General Public License v3.0 or later
 // https://www.gnu.org/licenses/gpl-3.0-standalone.html
 
-use crate::score::ScoreType;
+use crate::score::{LargeScoreType, ScoreType};
 
 pub(crate) const MIN_ASPIRATION_DEPTH: ScoreType = 1;
 pub(crate) const ASPIRATION_WINDOW: ScoreType = 50;
@@ -11,14 +11,89 @@ pub(crate) const ASPIRATION_WINDOW: ScoreType = 50;
 pub(crate) const MAX_RFP_DEPTH: ScoreType = 4;
 pub(crate) const RFP_MARGIN: ScoreType = 82;
 
+pub(crate) const MAX_RAZORING_DEPTH: ScoreType = 2;
+pub(crate) const RAZORING_MARGIN: ScoreType = 300;
+
+// ProbCut searches good captures at depth - reduction - 1 against beta + margin
+pub(crate) const PROBCUT_MIN_DEPTH: ScoreType = 5;
+pub(crate) const PROBCUT_DEPTH_REDUCTION: ScoreType = 4;
+pub(crate) const PROBCUT_MARGIN: ScoreType = 200;
+
 pub(crate) const IIR_MIN_DEPTH: ScoreType = 4;
 pub(crate) const IIR_DEPTH_REDUCTION: ScoreType = 1;
 
 pub(crate) const NMP_MIN_DEPTH: ScoreType = 3;
 pub(crate) const NMP_DEPTH_REDUCTION: ScoreType = 2;
+// The null move reduction grows by 1 for every NMP_DEPTH_DIVISOR plies of depth
+pub(crate) const NMP_DEPTH_DIVISOR: ScoreType = 4;
+// and by 1 for every NMP_EVAL_REDUCTION_DIVISOR the static eval beats beta, up to a maximum
+pub(crate) const NMP_EVAL_REDUCTION_DIVISOR: ScoreType = 200;
+pub(crate) const NMP_MAX_EVAL_REDUCTION: ScoreType = 3;
+// Null move cutoffs at this depth or higher are verified with a normal search
+pub(crate) const NMP_VERIFICATION_DEPTH: ScoreType = 10;
 
 pub(crate) const LMR_OFFSET: f64 = 0.2;
 pub(crate) const LMR_SCALING_FACTOR: f64 = 2.0;
+pub(crate) const LMR_CAPTURE_OFFSET: f64 = -0.1;
+pub(crate) const LMR_CAPTURE_SCALING_FACTOR: f64 = 3.0;
+// Minimum depth for late move reductions
+pub(crate) const LMR_MIN_DEPTH: ScoreType = 3;
+// Quiet history is divided by this to get the change in LMR reduction
+pub(crate) const LMR_HISTORY_DIVISOR: LargeScoreType = 8192;
+// Capture history is divided by this to get the change in LMR reduction
+pub(crate) const LMR_CAPTURE_HISTORY_DIVISOR: LargeScoreType = 6144;
 
 // Minimum threshold depth for LMP to be considered
 pub(crate) const LMP_MIN_THRESHOLD_DEPTH: ScoreType = 6;
+
+// Minimum depth for singular extensions to be considered
+pub(crate) const SE_MIN_DEPTH: ScoreType = 6;
+// How much shallower than the current depth the TT entry may be for singular extensions
+pub(crate) const SE_TT_DEPTH_MARGIN: ScoreType = 3;
+// Margin per depth below the TT score that the other moves must fail to reach
+pub(crate) const SE_MARGIN: ScoreType = 2;
+
+// Futility pruning is applied to quiet moves when the reduced depth is at most this
+pub(crate) const MAX_FP_DEPTH: ScoreType = 6;
+pub(crate) const FP_BASE_MARGIN: ScoreType = 100;
+pub(crate) const FP_MARGIN: ScoreType = 100;
+
+// History pruning is applied to quiet moves with a history below -margin * depth
+pub(crate) const MAX_HISTORY_PRUNING_DEPTH: ScoreType = 4;
+pub(crate) const HISTORY_PRUNING_MARGIN: LargeScoreType = 2048;
+
+// SEE pruning skips moves that lose more than margin * depth (quiets) or
+// margin * depth^2 (captures) material
+pub(crate) const MAX_SEE_PRUNING_DEPTH: ScoreType = 8;
+pub(crate) const SEE_QUIET_MARGIN: i32 = 60;
+pub(crate) const SEE_CAPTURE_MARGIN: i32 = 20;
+
+// Correction history stores corrections with this many units per centipawn, so small updates
+// aren't lost
+pub(crate) const CORRHIST_GRAIN: LargeScoreType = 256;
+// New search results are blended in with a weight of min(depth + 1, max weight) out of the scale
+pub(crate) const CORRHIST_WEIGHT_SCALE: LargeScoreType = 256;
+pub(crate) const CORRHIST_MAX_WEIGHT: LargeScoreType = 16;
+// Largest correction that can be stored, in centipawns
+pub(crate) const CORRHIST_MAX_CORRECTION: LargeScoreType = 128;
+
+// Sudden death time controls allocate time as if this many moves were left
+pub(crate) const TM_DEFAULT_MOVES_TO_GO: u32 = 25;
+// `movestogo` is capped at this so long repeating controls don't allocate too little time
+pub(crate) const TM_MAX_MOVES_TO_GO: u32 = 50;
+// Fraction of the increment added to the time for each move
+pub(crate) const TM_INCREMENT_FRACTION: f64 = 0.75;
+// The hard limit is base + moves to go * per move times the time allocated for a move, up to max
+pub(crate) const TM_HARD_LIMIT_BASE: f64 = 1.5;
+pub(crate) const TM_HARD_LIMIT_PER_MOVE: f64 = 0.1;
+pub(crate) const TM_MAX_HARD_LIMIT_SCALE: f64 = 4.0;
+// Neither limit may use more than this fraction of the remaining time
+pub(crate) const TM_MAX_TIME_FRACTION: f64 = 0.8;
+// The soft limit is scaled by the number of iterations the best move stayed the same
+pub(crate) const TM_STABILITY_SCALES: [f64; 5] = [2.5, 1.2, 0.9, 0.8, 0.75];
+// and grows by this much for every centipawn the score dropped since the last iteration
+pub(crate) const TM_SCORE_DROP_SCALE: f64 = 0.005;
+pub(crate) const TM_MAX_SCORE_DROP: LargeScoreType = 100;
+// and by (base - fraction) * scale, where fraction is the share of nodes spent on the best move
+pub(crate) const TM_NODE_FRACTION_BASE: f64 = 1.5;
+pub(crate) const TM_NODE_FRACTION_SCALE: f64 = 1.35;
@@ -107,9 +107,10 @@ pub(crate) fn bench(depth: u8, epd_file: &Option<String>) {
     };
 
     let mut nodes = 0u64;
-    let mut tt = Default::default();
+    let tt = Default::default();
     let mut hist = Default::default();
-    let mut search = Search::<LogNone>::new(&config, &mut tt, &mut hist);
+    let mut corr_hist = Default::default();
+    let mut search = Search::<LogNone>::new(&config, &tt, &mut hist, &mut corr_hist);
 
     let max_fen_width = benchmark_strings.iter().map(|s| s.len()).max().unwrap();
 